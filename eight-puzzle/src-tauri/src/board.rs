@@ -0,0 +1,402 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoardError {
+    Empty,
+    // A token that is not a tile number.
+    InvalidTile(String),
+    InvalidJson(String),
+    // Rows must all be as long as the first one.
+    RaggedRows {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    // Tiles are stored as bytes, which limits boards to 256 tiles.
+    TooLarge {
+        tiles: usize,
+    },
+    // The number of tiles does not match the width and height.
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    // Tiles are numbered 0 to width * height - 1, with 0 as the blank.
+    TileOutOfRange {
+        tile: u32,
+        tiles: usize,
+    },
+    // Every tile must appear exactly once, so a duplicate always leaves
+    // another tile missing.
+    DuplicateTile {
+        tile: u32,
+        missing: u32,
+    },
+}
+
+impl Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardError::Empty => write!(f, "the board is empty"),
+            BoardError::InvalidTile(token) => write!(f, "{:?} is not a tile number", token),
+            BoardError::InvalidJson(error) => write!(f, "invalid JSON board: {}", error),
+            BoardError::RaggedRows {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} has {} tiles, expected {} like the first row",
+                row + 1,
+                found,
+                expected
+            ),
+            BoardError::TooLarge { tiles } => {
+                write!(
+                    f,
+                    "boards of {} tiles are too large, at most 256 fit",
+                    tiles
+                )
+            }
+            BoardError::SizeMismatch { expected, found } => {
+                write!(f, "expected a board of {} tiles, found {}", expected, found)
+            }
+            BoardError::TileOutOfRange { tile, tiles } => write!(
+                f,
+                "tile {} is out of range, a board of {} tiles is numbered 0 to {}",
+                tile,
+                tiles,
+                tiles - 1
+            ),
+            BoardError::DuplicateTile { tile, missing } => write!(
+                f,
+                "tile {} appears more than once and tile {} is missing",
+                tile, missing
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+// A rectangular arrangement holding every tile from 0 (the blank) to
+// width * height - 1 exactly once. It is written as rows and read like a
+// request, so a deserialized board has been validated as well.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BoardInput", into = "BoardInput")]
+pub struct Board {
+    width: usize,
+    height: usize,
+    tiles: Vec<u8>,
+}
+
+impl Board {
+    pub fn new(width: usize, height: usize, tiles: Vec<u8>) -> Result<Board, BoardError> {
+        Board::validated(width, height, tiles.into_iter().map(u32::from).collect())
+    }
+
+    fn validated(width: usize, height: usize, values: Vec<u32>) -> Result<Board, BoardError> {
+        let count = width * height;
+
+        if count == 0 {
+            return Err(BoardError::Empty);
+        }
+
+        if values.len() != count {
+            return Err(BoardError::SizeMismatch {
+                expected: count,
+                found: values.len(),
+            });
+        }
+
+        if count > 256 {
+            return Err(BoardError::TooLarge { tiles: count });
+        }
+
+        let mut seen = vec![false; count];
+        let mut duplicate = None;
+
+        for &tile in &values {
+            if tile as usize >= count {
+                return Err(BoardError::TileOutOfRange { tile, tiles: count });
+            }
+
+            if seen[tile as usize] {
+                duplicate = duplicate.or(Some(tile));
+            }
+            seen[tile as usize] = true;
+        }
+
+        if let Some(tile) = duplicate {
+            let missing = seen.iter().position(|seen| !seen).unwrap() as u32;
+            return Err(BoardError::DuplicateTile { tile, missing });
+        }
+
+        Ok(Board {
+            width,
+            height,
+            tiles: values.into_iter().map(|tile| tile as u8).collect(),
+        })
+    }
+
+    fn from_rows(rows: Vec<Vec<u32>>) -> Result<Board, BoardError> {
+        let width = rows.first().map_or(0, |row| row.len());
+
+        for (row, tiles) in rows.iter().enumerate() {
+            if tiles.len() != width {
+                return Err(BoardError::RaggedRows {
+                    row,
+                    expected: width,
+                    found: tiles.len(),
+                });
+            }
+        }
+
+        let height = rows.len();
+        Board::validated(width, height, rows.concat())
+    }
+
+    // A single list of tiles is read as a square board if it can be, and as
+    // a single row otherwise.
+    fn from_flat(values: Vec<u32>) -> Result<Board, BoardError> {
+        let side = (values.len() as f64).sqrt().round() as usize;
+
+        if side * side == values.len() {
+            Board::validated(side, side, values)
+        } else {
+            Board::validated(values.len(), 1, values)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tiles(&self) -> &[u8] {
+        &self.tiles
+    }
+
+    pub fn into_tiles(self) -> Vec<u8> {
+        self.tiles
+    }
+}
+
+fn parse_row(row: &str) -> Result<Vec<u32>, BoardError> {
+    let row = row.trim();
+
+    // Without separators every digit is a tile of its own, as in "123804765".
+    if !row.contains(|c: char| c.is_whitespace() || c == ',') {
+        return row
+            .chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .ok_or_else(|| BoardError::InvalidTile(c.to_string()))
+            })
+            .collect();
+    }
+
+    row.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            token
+                .parse()
+                .map_err(|_| BoardError::InvalidTile(token.to_string()))
+        })
+        .collect()
+}
+
+// Reads "123804765", "1 2 3 / 8 0 4 / 7 6 5" (rows may also be separated by
+// new lines and tiles by commas), or a JSON array, either flat or of rows.
+// Boards without row separators are square if their number of tiles allows
+// it, and a single row otherwise, so a row of four tiles is written with a
+// trailing separator, as "1 2 3 0 /".
+impl FromStr for Board {
+    type Err = BoardError;
+
+    fn from_str(text: &str) -> Result<Board, BoardError> {
+        let text = text.trim();
+
+        if text.starts_with('[') {
+            let input: BoardInput = serde_json::from_str(text)
+                .map_err(|error| BoardError::InvalidJson(error.to_string()))?;
+            return Board::try_from(input);
+        }
+
+        let separated = text.contains(['/', '\n']);
+        let rows: Vec<&str> = text
+            .split(['/', '\n'])
+            .filter(|row| !row.trim().is_empty())
+            .collect();
+
+        match rows.len() {
+            0 => Err(BoardError::Empty),
+            1 if !separated => Board::from_flat(parse_row(rows[0])?),
+            _ => Board::from_rows(rows.into_iter().map(parse_row).collect::<Result<_, _>>()?),
+        }
+    }
+}
+
+// A board given as a single list of tiles, square if it can be.
+impl TryFrom<Vec<u8>> for Board {
+    type Error = BoardError;
+
+    fn try_from(tiles: Vec<u8>) -> Result<Board, BoardError> {
+        Board::from_flat(tiles.into_iter().map(u32::from).collect())
+    }
+}
+
+impl TryFrom<Vec<Vec<u8>>> for Board {
+    type Error = BoardError;
+
+    fn try_from(rows: Vec<Vec<u8>>) -> Result<Board, BoardError> {
+        Board::from_rows(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(u32::from).collect())
+                .collect(),
+        )
+    }
+}
+
+// How a board may be written in a request. Tiles are read as wider integers
+// so that out of range values are reported as such rather than as a type
+// error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BoardInput {
+    Text(String),
+    Flat(Vec<u32>),
+    Rows(Vec<Vec<u32>>),
+}
+
+impl TryFrom<BoardInput> for Board {
+    type Error = BoardError;
+
+    fn try_from(input: BoardInput) -> Result<Board, BoardError> {
+        match input {
+            BoardInput::Text(text) => text.parse(),
+            BoardInput::Flat(tiles) => Board::from_flat(tiles),
+            BoardInput::Rows(rows) => Board::from_rows(rows),
+        }
+    }
+}
+
+impl From<Board> for BoardInput {
+    fn from(board: Board) -> BoardInput {
+        BoardInput::Rows(
+            board
+                .tiles
+                .chunks(board.width)
+                .map(|row| row.iter().map(|&tile| u32::from(tile)).collect())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_boards() {
+        let expected = Board::new(3, 3, vec![1, 2, 3, 8, 0, 4, 7, 6, 5]).unwrap();
+
+        for text in [
+            "123804765",
+            "123/804/765",
+            "1 2 3 / 8 0 4 / 7 6 5",
+            "1,2,3\n8,0,4\n7,6,5\n",
+            "[1, 2, 3, 8, 0, 4, 7, 6, 5]",
+            "[[1, 2, 3], [8, 0, 4], [7, 6, 5]]",
+        ] {
+            assert_eq!(text.parse::<Board>(), Ok(expected.clone()), "{}", text);
+        }
+
+        let rectangle: Board = "1 2 3 4 / 5 6 7 0".parse().unwrap();
+        assert_eq!((rectangle.width(), rectangle.height()), (4, 2));
+
+        let fifteen: Board = "1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 0".parse().unwrap();
+        assert_eq!(fifteen.width(), 4);
+
+        // A single row reads the same as text and as JSON rows.
+        let row = Board::new(3, 1, vec![1, 2, 0]).unwrap();
+        for text in ["1 2 0", "120", "[1, 2, 0]", "[[1, 2, 0]]"] {
+            assert_eq!(text.parse::<Board>(), Ok(row.clone()), "{}", text);
+        }
+        let square: Board = "1 2 3 0".parse().unwrap();
+        assert_eq!((square.width(), square.height()), (2, 2));
+        let long_row: Board = "1 2 3 0 /".parse().unwrap();
+        assert_eq!((long_row.width(), long_row.height()), (4, 1));
+
+        assert_eq!(
+            Board::try_from(vec![vec![1, 2], vec![3, 0]]),
+            Ok(Board::new(2, 2, vec![1, 2, 3, 0]).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_invalid_boards() {
+        for (text, error) in [
+            ("", BoardError::Empty),
+            ("12a804765", BoardError::InvalidTile("a".to_string())),
+            ("1 2 x", BoardError::InvalidTile("x".to_string())),
+            ("12380476", BoardError::TileOutOfRange { tile: 8, tiles: 8 }),
+            (
+                "123/80/765",
+                BoardError::RaggedRows {
+                    row: 1,
+                    expected: 3,
+                    found: 2,
+                },
+            ),
+            (
+                "123894765",
+                BoardError::TileOutOfRange { tile: 9, tiles: 9 },
+            ),
+            (
+                "[1, 2, 3, 8, 300, 4, 7, 6, 5]",
+                BoardError::TileOutOfRange {
+                    tile: 300,
+                    tiles: 9,
+                },
+            ),
+            (
+                "123814765",
+                BoardError::DuplicateTile {
+                    tile: 1,
+                    missing: 0,
+                },
+            ),
+        ] {
+            assert_eq!(text.parse::<Board>(), Err(error), "{:?}", text);
+        }
+
+        assert!(matches!(
+            "[1, 2".parse::<Board>(),
+            Err(BoardError::InvalidJson(_))
+        ));
+        assert_eq!(
+            Board::new(3, 2, vec![1, 2, 3, 0]),
+            Err(BoardError::SizeMismatch {
+                expected: 6,
+                found: 4
+            })
+        );
+
+        // Deserializing goes through the same checks, so fields cannot be
+        // filled in directly.
+        assert!(
+            serde_json::from_str::<Board>(r#"{"width": 2, "height": 1, "tiles": [1, 1]}"#).is_err()
+        );
+        assert!(serde_json::from_str::<Board>("[[1, 1], [2, 3]]").is_err());
+        let rectangle = Board::new(3, 2, vec![1, 2, 3, 4, 5, 0]).unwrap();
+        let json = serde_json::to_string(&rectangle).unwrap();
+        assert_eq!(json, "[[1,2,3],[4,5,0]]");
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), rectangle);
+    }
+}
@@ -0,0 +1,736 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use search::{
+    graphviz::{self, Drawing},
+    DepthLimitedOutcome, ExpansionLimit, SearchEvent, SearchObserver,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{Board, BoardError, BoardInput},
+    eight_puzzle::{EightPuzzleState, Move, PuzzleError, SearchResult, Tree},
+    generator::{self, Generated, GeneratorError, GeneratorParameters, Scramble},
+    heuristic_quality::{Analysis, HeuristicAnalyzer, NamedHeuristic},
+    pattern_database::{PatternDatabase, PatternError},
+    ranking::{self, RankError},
+    state_space::{StateSpace, Summary},
+    walking_distance::WalkingDistance,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    BreadthFirst,
+    DepthFirst,
+    DepthLimited,
+    IterativeDeepening,
+    UniformCost,
+    AStar,
+    IdaStar,
+    Bidirectional,
+    // Breadth-first search over ranked boards, which is much faster but only
+    // reports progress, and is limited to boards of up to ten tiles.
+    RankedBreadthFirst,
+}
+
+// Only used by A* and IDA*.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Heuristic {
+    #[default]
+    Manhattan,
+    MisplacedTiles,
+    LinearConflict,
+    WalkingDistance,
+    PatternDatabase,
+}
+
+// A heuristic along with the tables it uses.
+pub type HeuristicFn = Box<dyn Fn(&EightPuzzleState) -> usize>;
+
+impl Heuristic {
+    // Builds the tables the heuristic needs for the target of `game`, if any.
+    // Setting `cancel` stops a pattern database build.
+    pub fn build(
+        self,
+        game: &EightPuzzleState,
+        patterns: &[Vec<u8>],
+        cache_dir: Option<&Path>,
+        cancel: &AtomicBool,
+    ) -> Result<HeuristicFn, PatternError> {
+        let (width, height, target) = (game.width(), game.height(), game.target());
+
+        Ok(match self {
+            Heuristic::Manhattan => Box::new(EightPuzzleState::manhattan_distance),
+            Heuristic::MisplacedTiles => Box::new(EightPuzzleState::misplaced_tiles),
+            Heuristic::LinearConflict => Box::new(EightPuzzleState::linear_conflict),
+            Heuristic::WalkingDistance => {
+                let walking_distance = WalkingDistance::new(width, height, target);
+                Box::new(move |state| walking_distance.distance(state.board()))
+            }
+            Heuristic::PatternDatabase => {
+                let database = match cache_dir {
+                    Some(dir) => PatternDatabase::load_or_build(
+                        dir, width, height, target, patterns, cancel,
+                    )?,
+                    None => PatternDatabase::build(width, height, target, patterns, cancel)?,
+                };
+                Box::new(move |state| database.distance(state.board()))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Limits {
+    // Required by depth-limited search, and the deepest pass that iterative
+    // deepening tries.
+    pub depth: Option<usize>,
+    // Interrupts any search after this many expansions.
+    pub nodes: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VisualizationOptions {
+    pub enabled: bool,
+    // Keeps only the first steps of every iteration, so that large trees do
+    // not have to be drawn by the webview.
+    pub max_steps: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolveRequest {
+    pub start: BoardInput,
+    pub goal: BoardInput,
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub heuristic: Heuristic,
+    // Disjoint tile groups of the pattern database heuristic, such as 1-7 and
+    // 8-15 for the 15-puzzle. Chunks of six tiles by default.
+    #[serde(default)]
+    pub patterns: Option<Vec<Vec<u8>>>,
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub visualization: VisualizationOptions,
+    // Sends the tree of breadth-first and depth-first search back with the
+    // result, as a list of nodes that refer to their parents by index.
+    #[serde(default)]
+    pub include_tree: bool,
+}
+
+// A pass of iterative deepening or IDA*, bounded by a depth limit or an f
+// threshold respectively.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Iteration {
+    pub bound: usize,
+    pub nodes_expanded: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolveResponse {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    // Only reported by depth-limited search.
+    pub outcome: Option<DepthLimitedOutcome>,
+    pub iterations: Vec<Iteration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tree: Option<Tree>,
+}
+
+// Sent to the window in batches while a search runs. The counts are totals
+// so far, not the increase since the last update.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchUpdate {
+    pub drawings: Vec<Drawing>,
+    pub nodes_expanded: usize,
+    pub nodes_generated: usize,
+    pub frontier: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SolveError {
+    Board(BoardError),
+    Puzzle(PuzzleError),
+    Pattern(PatternError),
+    Rank(RankError),
+    Generator(GeneratorError),
+    MissingDepthLimit,
+    // The search thread panicked before it could report a result.
+    Panicked,
+}
+
+impl Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::Board(error) => error.fmt(f),
+            SolveError::Puzzle(error) => error.fmt(f),
+            SolveError::Pattern(error) => error.fmt(f),
+            SolveError::Rank(error) => error.fmt(f),
+            SolveError::Generator(error) => error.fmt(f),
+            SolveError::MissingDepthLimit => write!(f, "depth-limited search needs a depth limit"),
+            SolveError::Panicked => write!(f, "the search stopped unexpectedly"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl From<BoardError> for SolveError {
+    fn from(error: BoardError) -> SolveError {
+        SolveError::Board(error)
+    }
+}
+
+impl From<PuzzleError> for SolveError {
+    fn from(error: PuzzleError) -> SolveError {
+        SolveError::Puzzle(error)
+    }
+}
+
+impl From<PatternError> for SolveError {
+    fn from(error: PatternError) -> SolveError {
+        SolveError::Pattern(error)
+    }
+}
+
+impl From<RankError> for SolveError {
+    fn from(error: RankError) -> SolveError {
+        SolveError::Rank(error)
+    }
+}
+
+impl From<GeneratorError> for SolveError {
+    fn from(error: GeneratorError) -> SolveError {
+        SolveError::Generator(error)
+    }
+}
+
+impl SolveRequest {
+    // Checks the request before anything is started. Pattern databases are
+    // cached in `cache_dir` if there is one, and built for every search
+    // otherwise.
+    pub fn prepare(self, cache_dir: Option<PathBuf>) -> Result<PreparedSearch, SolveError> {
+        let game =
+            EightPuzzleState::start(Board::try_from(self.start)?, Board::try_from(self.goal)?)?;
+
+        if self.algorithm == Algorithm::DepthLimited && self.limits.depth.is_none() {
+            return Err(SolveError::MissingDepthLimit);
+        }
+
+        let patterns = self
+            .patterns
+            .unwrap_or_else(|| PatternDatabase::default_groups(game.board().len()));
+        if self.algorithm == Algorithm::RankedBreadthFirst {
+            ranking::check(game.board().len())?;
+        }
+
+        if self.heuristic == Heuristic::PatternDatabase {
+            PatternDatabase::check(game.width(), game.height(), &patterns)?;
+        }
+
+        Ok(PreparedSearch {
+            game,
+            algorithm: self.algorithm,
+            heuristic: self.heuristic,
+            patterns,
+            cache_dir,
+            limits: self.limits,
+            visualization: self.visualization,
+            include_tree: self.include_tree,
+        })
+    }
+}
+
+// Explores every board that can reach `goal`, for goals of up to ten tiles.
+pub fn analyze_target(goal: BoardInput) -> Result<Summary, SolveError> {
+    let goal = Board::try_from(goal)?;
+    let game = EightPuzzleState::start(goal.clone(), goal)?;
+
+    Ok(StateSpace::explore(&game)?.summary())
+}
+
+// Grades the built-in heuristics against the exact distances to `goal`, with
+// the default pattern groups.
+pub fn analyze_heuristics(
+    goal: BoardInput,
+    heuristics: Vec<Heuristic>,
+    cache_dir: Option<PathBuf>,
+) -> Result<Analysis, SolveError> {
+    let goal = Board::try_from(goal)?;
+    let game = EightPuzzleState::start(goal.clone(), goal)?;
+    let analyzer = HeuristicAnalyzer::new(&game)?;
+
+    let patterns = PatternDatabase::default_groups(game.board().len());
+    let built = heuristics
+        .iter()
+        .map(|heuristic| {
+            heuristic.build(
+                &game,
+                &patterns,
+                cache_dir.as_deref(),
+                &AtomicBool::new(false),
+            )
+        })
+        .collect::<Result<Vec<_>, PatternError>>()?;
+    let names: Vec<String> = heuristics
+        .iter()
+        .map(|heuristic| format!("{:?}", heuristic))
+        .collect();
+
+    let named: Vec<NamedHeuristic<'_>> = names
+        .iter()
+        .zip(&built)
+        .map(|(name, heuristic_fn)| (name.as_str(), heuristic_fn.as_ref()))
+        .collect();
+    Ok(analyzer.analyze(&named))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateRequest {
+    pub goal: BoardInput,
+    // Taken from the clock if there is none, and reported with the boards
+    // either way.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub count: usize,
+    pub scramble: Scramble,
+}
+
+impl GenerateRequest {
+    pub fn run(self) -> Result<Generated, SolveError> {
+        let goal = Board::try_from(self.goal)?;
+        let game = EightPuzzleState::start(goal.clone(), goal)?;
+
+        let seed = self.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+        let parameters = GeneratorParameters {
+            seed,
+            count: self.count,
+            scramble: self.scramble,
+        };
+
+        Ok(generator::generate(&game, &parameters)?)
+    }
+}
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+// Collects the drawing and progress of a running search and hands them to
+// `send` in batches, and interrupts the search once `cancel` is set.
+struct Streaming<'a, F: FnMut(SearchUpdate)> {
+    game: &'a EightPuzzleState,
+    visualization: &'a VisualizationOptions,
+    cancel: &'a AtomicBool,
+    send: F,
+    pending: SearchUpdate,
+    last_sent: Instant,
+    // Steps drawn in the current iteration, and whether the last one was
+    // kept so that amendments to it are kept as well.
+    steps: usize,
+    drawing: bool,
+}
+
+impl<F: FnMut(SearchUpdate)> Streaming<'_, F> {
+    fn flush(&mut self) {
+        let update = SearchUpdate {
+            drawings: std::mem::take(&mut self.pending.drawings),
+            ..self.pending
+        };
+
+        (self.send)(update);
+        self.last_sent = Instant::now();
+    }
+}
+
+impl<F: FnMut(SearchUpdate)> SearchObserver<Move, EightPuzzleState> for Streaming<'_, F> {
+    fn event(&mut self, event: SearchEvent<'_, Move, EightPuzzleState>) {
+        match event {
+            SearchEvent::NodeExpanded { .. } => self.pending.nodes_expanded += 1,
+            SearchEvent::NodeGenerated { .. } => self.pending.nodes_generated += 1,
+            SearchEvent::FrontierSnapshot { frontier, .. } => self.pending.frontier = frontier,
+            _ => {}
+        }
+
+        if self.visualization.enabled {
+            let max_steps = self.visualization.max_steps.unwrap_or(usize::MAX);
+
+            match graphviz::draw(self.game, &event) {
+                Some(Drawing::Restart) => {
+                    self.steps = 0;
+                    self.drawing = true;
+                    self.pending.drawings.push(Drawing::Restart);
+                }
+                Some(Drawing::Step(statements)) => {
+                    self.drawing = self.steps < max_steps;
+                    if self.drawing {
+                        self.steps += 1;
+                        self.pending.drawings.push(Drawing::Step(statements));
+                    }
+                }
+                Some(amend) if self.drawing => self.pending.drawings.push(amend),
+                _ => {}
+            }
+        }
+
+        if self.last_sent.elapsed() >= UPDATE_INTERVAL {
+            self.flush();
+        }
+    }
+
+    fn interrupted(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+// A validated request, ready to be run.
+pub struct PreparedSearch {
+    game: EightPuzzleState,
+    algorithm: Algorithm,
+    heuristic: Heuristic,
+    patterns: Vec<Vec<u8>>,
+    cache_dir: Option<PathBuf>,
+    limits: Limits,
+    visualization: VisualizationOptions,
+    include_tree: bool,
+}
+
+impl PreparedSearch {
+    // Pattern groups have been checked by `prepare`, but building the
+    // database can still be cancelled, and saving or loading it can fail.
+    fn heuristic(&self, cancel: &AtomicBool) -> Result<HeuristicFn, PatternError> {
+        self.heuristic.build(
+            &self.game,
+            &self.patterns,
+            self.cache_dir.as_deref(),
+            cancel,
+        )
+    }
+
+    // Runs the search, passing its progress to `send` as it goes. Setting
+    // `cancel` stops it early with the statistics gathered so far, or with
+    // `PatternError::Cancelled` while its pattern database is being built.
+    pub fn run(
+        &self,
+        cancel: &AtomicBool,
+        send: impl FnMut(SearchUpdate),
+    ) -> Result<SolveResponse, SolveError> {
+        let game = &self.game;
+
+        let mut streaming = Streaming {
+            game,
+            visualization: &self.visualization,
+            cancel,
+            send,
+            pending: SearchUpdate::default(),
+            last_sent: Instant::now(),
+            steps: 0,
+            drawing: true,
+        };
+        let mut observer =
+            ExpansionLimit::new(&mut streaming, self.limits.nodes.unwrap_or(usize::MAX));
+
+        let mut outcome = None;
+        let mut iterations = Vec::new();
+        let mut tree = None;
+
+        let result = match self.algorithm {
+            Algorithm::BreadthFirst => {
+                let (result, searched) = game.build_tree_breadth_first(&mut observer);
+                tree = Some(searched);
+                result
+            }
+            Algorithm::DepthFirst => {
+                let (result, searched) = game.build_tree_depth_first(&mut observer);
+                tree = Some(searched);
+                result
+            }
+            Algorithm::DepthLimited => {
+                let limit = self.limits.depth.unwrap();
+                let (result, depth_limited) = game.depth_limited_search(limit, &mut observer);
+                outcome = Some(depth_limited);
+                result
+            }
+            Algorithm::IterativeDeepening => {
+                let max_limit = self.limits.depth.unwrap_or(usize::MAX);
+                let (result, passes) = game.iterative_deepening_search(max_limit, &mut observer);
+                iterations = passes
+                    .iter()
+                    .map(|pass| Iteration {
+                        bound: pass.limit,
+                        nodes_expanded: pass.nodes_expanded,
+                    })
+                    .collect();
+                result
+            }
+            Algorithm::UniformCost => game.uniform_cost_search(&mut observer),
+            Algorithm::AStar => game.astar_search(self.heuristic(cancel)?, &mut observer),
+            Algorithm::IdaStar => {
+                let (result, passes) = game.ida_star_search(self.heuristic(cancel)?, &mut observer);
+                iterations = passes
+                    .iter()
+                    .map(|pass| Iteration {
+                        bound: pass.threshold,
+                        nodes_expanded: pass.nodes_expanded,
+                    })
+                    .collect();
+                result
+            }
+            Algorithm::Bidirectional => game.bidirectional_breadth_first(&mut observer),
+            // Board sizes have been checked by `prepare`.
+            Algorithm::RankedBreadthFirst => game.ranked_breadth_first(&mut observer).unwrap(),
+        };
+
+        streaming.flush();
+
+        Ok(SolveResponse {
+            result,
+            outcome,
+            iterations,
+            tree: tree.filter(|_| self.include_tree),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heuristic_quality::Relation;
+
+    fn request() -> SolveRequest {
+        serde_json::from_str(
+            r#"{
+                "start": "1 2 3 / 0 4 6 / 7 5 8",
+                "goal": [[1, 2, 3], [4, 5, 6], [7, 8, 0]],
+                "algorithm": "IdaStar",
+                "heuristic": "MisplacedTiles",
+                "visualization": { "enabled": true, "max_steps": 2 }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn solve(request: SolveRequest) -> SolveResponse {
+        request
+            .prepare(None)
+            .unwrap()
+            .run(&AtomicBool::new(false), |_| {})
+            .unwrap()
+    }
+
+    #[test]
+    fn test_solve_requests() {
+        let mut updates = Vec::new();
+        let response = request()
+            .prepare(None)
+            .unwrap()
+            .run(&AtomicBool::new(false), |update| updates.push(update))
+            .unwrap();
+        assert_eq!(response.result.cost, 3);
+
+        let drawings: Vec<Drawing> = updates
+            .into_iter()
+            .flat_map(|update| update.drawings)
+            .collect();
+        let restarts = drawings
+            .iter()
+            .filter(|drawing| **drawing == Drawing::Restart)
+            .count();
+        assert_eq!(restarts, response.iterations.len());
+        assert!(drawings
+            .split(|drawing| *drawing == Drawing::Restart)
+            .all(|iteration| iteration
+                .iter()
+                .filter(|drawing| matches!(drawing, Drawing::Step(_)))
+                .count()
+                <= 2));
+
+        let response = solve(SolveRequest {
+            algorithm: Algorithm::BreadthFirst,
+            limits: Limits {
+                depth: None,
+                nodes: Some(1),
+            },
+            ..request()
+        });
+        assert!(response.result.interrupted);
+
+        let error = SolveRequest {
+            algorithm: Algorithm::DepthLimited,
+            ..request()
+        }
+        .prepare(None);
+        assert_eq!(error.err(), Some(SolveError::MissingDepthLimit));
+    }
+
+    #[test]
+    fn test_cancelled_search() {
+        // A cancelled search stops before its first expansion but still
+        // sends its final progress.
+        let mut last = None;
+        let response = request()
+            .prepare(None)
+            .unwrap()
+            .run(&AtomicBool::new(true), |update| last = Some(update))
+            .unwrap();
+        assert!(response.result.interrupted);
+        assert_eq!(response.result.stats.nodes_expanded, 0);
+        assert_eq!(last.unwrap().nodes_generated, 1);
+    }
+
+    #[test]
+    fn test_invalid_boards() {
+        let error = SolveRequest {
+            goal: BoardInput::Text("123/456/788".to_string()),
+            ..request()
+        }
+        .prepare(None);
+        assert_eq!(
+            error.err(),
+            Some(SolveError::Board(BoardError::DuplicateTile {
+                tile: 8,
+                missing: 0
+            }))
+        );
+
+        let error = SolveRequest {
+            goal: BoardInput::Flat(vec![1, 2, 3, 0]),
+            ..request()
+        }
+        .prepare(None);
+        assert_eq!(
+            error.err(),
+            Some(SolveError::Puzzle(PuzzleError::SizeMismatch {
+                start: (3, 3),
+                target: (2, 2)
+            }))
+        );
+    }
+
+    #[test]
+    fn test_pattern_database_requests() {
+        let pattern_request = SolveRequest {
+            algorithm: Algorithm::AStar,
+            heuristic: Heuristic::PatternDatabase,
+            patterns: Some(vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]]),
+            ..request()
+        };
+        let response = solve(pattern_request.clone());
+        assert_eq!(response.result.cost, 3);
+
+        // Cancelling stops the pattern database before the search starts.
+        let error = pattern_request
+            .clone()
+            .prepare(None)
+            .unwrap()
+            .run(&AtomicBool::new(true), |_| {});
+        assert_eq!(
+            error.err(),
+            Some(SolveError::Pattern(PatternError::Cancelled))
+        );
+
+        let error = SolveRequest {
+            patterns: Some(vec![vec![1, 2, 3], vec![3, 4]]),
+            ..pattern_request
+        }
+        .prepare(None);
+        assert_eq!(
+            error.err(),
+            Some(SolveError::Pattern(PatternError::InvalidTile(3)))
+        );
+    }
+
+    #[test]
+    fn test_ranked_requests() {
+        let ranked_request = SolveRequest {
+            algorithm: Algorithm::RankedBreadthFirst,
+            ..request()
+        };
+        let mut last = None;
+        let response = ranked_request
+            .clone()
+            .prepare(None)
+            .unwrap()
+            .run(&AtomicBool::new(false), |update| last = Some(update))
+            .unwrap();
+        assert_eq!(response.result.cost, 3);
+        assert!(last.unwrap().nodes_generated > response.result.stats.nodes_expanded);
+
+        let error = SolveRequest {
+            start: BoardInput::Text("1 2 3 4 / 5 6 7 8 / 9 10 11 0".to_string()),
+            goal: BoardInput::Text("1 2 3 4 / 5 6 7 8 / 9 10 0 11".to_string()),
+            ..ranked_request
+        }
+        .prepare(None);
+        assert_eq!(
+            error.err(),
+            Some(SolveError::Rank(RankError::TooManyTiles { tiles: 12 }))
+        );
+    }
+
+    #[test]
+    fn test_tree_requests() {
+        let response = solve(SolveRequest {
+            algorithm: Algorithm::BreadthFirst,
+            include_tree: true,
+            ..request()
+        });
+        let tree = response.tree.unwrap();
+        assert_eq!(tree.path_to(tree.len() - 1).1, response.result.states);
+
+        assert!(solve(request()).tree.is_none());
+    }
+
+    #[test]
+    fn test_analyze_target() {
+        let summary = analyze_target(request().goal).unwrap();
+        assert_eq!(summary.diameter, 31);
+    }
+
+    #[test]
+    fn test_analyze_heuristics() {
+        let analysis = analyze_heuristics(
+            BoardInput::Text("1 2 3 / 4 5 0".to_string()),
+            vec![Heuristic::Manhattan, Heuristic::PatternDatabase],
+            None,
+        )
+        .unwrap();
+        assert_eq!(analysis.states, 360);
+        assert!(analysis
+            .reports
+            .iter()
+            .all(|report| report.admissible && report.consistent));
+        assert_eq!(analysis.dominance[0].relation, Relation::DominatedBy);
+    }
+
+    #[test]
+    fn test_generate_requests() {
+        let request: GenerateRequest = serde_json::from_str(
+            r#"{ "goal": "1 2 / 3 0", "count": 2, "scramble": { "Distance": 6 } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request.clone().run().err(),
+            Some(SolveError::Generator(GeneratorError::NotEnoughBoards {
+                distance: 6,
+                available: 1
+            }))
+        );
+
+        let generated = GenerateRequest {
+            count: 1,
+            ..request
+        }
+        .run()
+        .unwrap();
+        assert_eq!(generated.boards[0].board, vec![0, 3, 2, 1]);
+        assert_eq!(generated.parameters.count, 1);
+    }
+}
@@ -1,11 +1,17 @@
-use std::{
-    collections::{HashSet, VecDeque},
-    task::Wake,
-};
+use std::{fmt::Display, mem::size_of};
 
+use search::{
+    DeepeningIteration, DepthLimitedOutcome, IdaStarIteration, Problem, ReversibleProblem,
+    SearchObserver,
+};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::{
+    board::{Board, BoardError},
+    ranking::{RankError, RankedTree},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Move {
     Left,
     Up,
@@ -13,31 +19,102 @@ pub enum Move {
     Right,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EightPuzzleState {
-    board: [u8; 9],
-    target: [u8; 9],
+impl Move {
+    // The move that undoes this one.
+    pub fn inverse(&self) -> Move {
+        match self {
+            Move::Left => Move::Right,
+            Move::Up => Move::Down,
+            Move::Down => Move::Up,
+            Move::Right => Move::Left,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PuzzleError {
+    // Only half of all tile permutations are reachable from a given board, so a
+    // search from the other half would run until it exhausts the state space.
+    Unsolvable,
+    // The start and target boards have different dimensions, given as
+    // (width, height).
+    SizeMismatch {
+        start: (usize, usize),
+        target: (usize, usize),
+    },
+}
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleError::Unsolvable => write!(f, "target is not reachable from the start board"),
+            PuzzleError::SizeMismatch { start, target } => write!(
+                f,
+                "the start board is {}x{} but the target is {}x{}",
+                start.0, start.1, target.0, target.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+// Search results report the boards along the solution rather than whole
+// states, since every state shares the same dimensions and target.
+pub type SearchResult = search::SearchResult<Move, Vec<u8>>;
+
+fn boards(result: search::SearchResult<Move, EightPuzzleState>) -> SearchResult {
+    result.map_states(|state| state.board)
+}
+
+// Search trees hold boards for the same reason.
+pub type Tree = search::SearchTree<Move, Vec<u8>>;
+
+fn board_tree((result, tree): search::TreeResult<Move, EightPuzzleState>) -> (SearchResult, Tree) {
+    (boards(result), tree.map_states(|state| state.board))
+}
+
+// Length of the longest strictly increasing subsequence of `values`.
+fn longest_increasing_subsequence(values: &[usize]) -> usize {
+    let mut lengths: Vec<usize> = Vec::with_capacity(values.len());
+
+    for (i, value) in values.iter().enumerate() {
+        let longest_before = (0..i)
+            .filter(|&j| values[j] < *value)
+            .map(|j| lengths[j])
+            .max()
+            .unwrap_or(0);
+        lengths.push(longest_before + 1);
+    }
+
+    lengths.into_iter().max().unwrap_or(0)
+}
 
-    move_history: Vec<Move>,
-    children: Vec<EightPuzzleState>,
+// Only built by `start`, so that every board it holds has been validated.
+#[derive(Debug, Clone, Serialize)]
+pub struct EightPuzzleState {
+    width: usize,
+    height: usize,
 
-    graphviz: bool,
+    board: Vec<u8>,
+    target: Vec<u8>,
 }
 
 impl ToString for EightPuzzleState {
     fn to_string(&self) -> String {
-        format!(
-            "     {}  {}  {}     \\n     {}  {}  {}     \\n     {}  {}  {}     ",
-            self.board[0],
-            self.board[1],
-            self.board[2],
-            self.board[3],
-            self.board[4],
-            self.board[5],
-            self.board[6],
-            self.board[7],
-            self.board[8],
-        )
+        let tile_width = (self.board.len() - 1).to_string().len();
+
+        self.board
+            .chunks(self.width)
+            .map(|row| {
+                let tiles: Vec<String> = row
+                    .iter()
+                    .map(|tile| format!("{:>width$}", tile, width = tile_width))
+                    .collect();
+                format!("     {}     ", tiles.join("  "))
+            })
+            .collect::<Vec<String>>()
+            .join("\\n")
     }
 }
 
@@ -48,508 +125,676 @@ impl PartialEq for EightPuzzleState {
 }
 
 impl EightPuzzleState {
-    fn new(graphviz: bool, state: [u8; 9], target: [u8; 9]) -> EightPuzzleState {
+    fn new(width: usize, height: usize, state: Vec<u8>, target: Vec<u8>) -> EightPuzzleState {
+        assert_eq!(state.len(), width * height);
+        assert_eq!(target.len(), width * height);
+
         EightPuzzleState {
+            width,
+            height,
             board: state,
             target,
-            move_history: Vec::new(),
-            children: Vec::new(),
-            graphviz,
         }
     }
 
-    fn int_repr(&self) -> u64 {
-        let mut repr = 0;
-        for i in 0..9 {
-            repr += (self.board[i] as u64) << (4 * i);
-        }
-        repr
-    }
-
-    fn repr(&self) -> String {
-        format!(
-            "{}{}{}{}{}{}{}{}{}",
-            self.board[0],
-            self.board[1],
-            self.board[2],
-            self.board[3],
-            self.board[4],
-            self.board[5],
-            self.board[6],
-            self.board[7],
-            self.board[8],
-        )
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn board(&self) -> &[u8] {
+        &self.board
+    }
+
+    pub fn target(&self) -> &[u8] {
+        &self.target
     }
 
-    pub fn last_move(&self) -> Option<Move> {
-        self.move_history.last().cloned()
+    // The same puzzle at another board of the same size, which need not be
+    // able to reach the target.
+    pub fn with_board(&self, board: Vec<u8>) -> Result<EightPuzzleState, BoardError> {
+        let board = Board::new(self.width, self.height, board)?;
+        Ok(EightPuzzleState::new(
+            self.width,
+            self.height,
+            board.into_tiles(),
+            self.target.clone(),
+        ))
+    }
+
+    // The same puzzle, already solved.
+    pub fn at_target(&self) -> EightPuzzleState {
+        EightPuzzleState::new(
+            self.width,
+            self.height,
+            self.target.clone(),
+            self.target.clone(),
+        )
     }
 
+    // Boards are validated by `start` and `with_board`, so there always is a
+    // blank.
     pub fn blank_position(&self) -> usize {
-        self.board.iter().position(|&x| x == 0).unwrap()
+        self.board
+            .iter()
+            .position(|&x| x == 0)
+            .expect("validated boards always have a blank")
     }
 
     pub fn misplaced_tiles(&self) -> usize {
         let mut count = 0;
-        for i in 1..9 {
-            if self.board[i] != self.target[i] {
+        for i in 0..self.board.len() {
+            if self.board[i] != 0 && self.board[i] != self.target[i] {
                 count += 1;
             }
         }
         count
     }
 
+    // Where every tile belongs, indexed by tile.
+    fn target_positions(&self) -> Vec<usize> {
+        let mut target_positions = vec![0; self.target.len()];
+        for (index, &tile) in self.target.iter().enumerate() {
+            target_positions[tile as usize] = index;
+        }
+        target_positions
+    }
+
     pub fn manhattan_distance(&self) -> usize {
-        let source = self.board;
-        let target = self.target;
+        let target_positions = self.target_positions();
 
         let mut distance = 0;
 
-        for i in 1..9 {
-            let source_index = source.iter().position(|&x| x == i as u8).unwrap();
-            let target_index = target.iter().position(|&x| x == i as u8).unwrap();
+        for (source_index, &tile) in self.board.iter().enumerate() {
+            if tile == 0 {
+                continue;
+            }
 
-            let source_x = source_index % 3;
-            let source_y = source_index / 3;
+            let target_index = target_positions[tile as usize];
 
-            let target_x = target_index % 3;
-            let target_y = target_index / 3;
+            let source_x = source_index % self.width;
+            let source_y = source_index / self.width;
 
-            distance += (source_x as i8 - target_x as i8).abs() as usize;
-            distance += (source_y as i8 - target_y as i8).abs() as usize;
+            let target_x = target_index % self.width;
+            let target_y = target_index / self.width;
+
+            distance += source_x.abs_diff(target_x);
+            distance += source_y.abs_diff(target_y);
         }
 
         distance
     }
 
-    pub fn is_terminal(&self) -> bool {
-        self.children == vec![]
-    }
-
-    pub fn discover_neighbours(&mut self, reverse: bool) -> Vec<&mut EightPuzzleState> {
-        let mut next_states: Vec<EightPuzzleState> = Vec::new();
+    // Manhattan distance plus two moves for every tile that has to step out of
+    // its goal row or column to let the others in it pass. Only the tiles
+    // outside the longest run already in goal order have to step aside, so
+    // three reversed tiles cost four extra moves rather than two per pair.
+    pub fn linear_conflict(&self) -> usize {
+        let target_positions = self.target_positions();
+        let (width, height) = (self.width, self.height);
+
+        // Goal columns of the tiles in every row that belong in it, in the
+        // order they appear, and the same for goal rows within columns.
+        let mut conflicts = 0;
+
+        for row in 0..height {
+            let columns: Vec<usize> = (0..width)
+                .map(|x| self.board[row * width + x])
+                .filter(|&tile| tile != 0)
+                .map(|tile| target_positions[tile as usize])
+                .filter(|&target| target / width == row)
+                .map(|target| target % width)
+                .collect();
+            conflicts += columns.len() - longest_increasing_subsequence(&columns);
+        }
 
-        let mut next_state = self.clone();
-        if next_state.make_move(Move::Left) == true {
-            next_states.push(next_state);
+        for column in 0..width {
+            let rows: Vec<usize> = (0..height)
+                .map(|y| self.board[y * width + column])
+                .filter(|&tile| tile != 0)
+                .map(|tile| target_positions[tile as usize])
+                .filter(|&target| target % width == column)
+                .map(|target| target / width)
+                .collect();
+            conflicts += rows.len() - longest_increasing_subsequence(&rows);
         }
 
-        let mut next_state = self.clone();
-        if next_state.make_move(Move::Up) == true {
-            next_states.push(next_state)
+        self.manhattan_distance() + 2 * conflicts
+    }
+
+    // Number of pairs of tiles that appear in a different order on `board` than
+    // on `target`, ignoring the blank.
+    pub fn inversions(&self) -> usize {
+        let mut target_index = vec![0; self.target.len()];
+        for (index, &tile) in self.target.iter().enumerate() {
+            target_index[tile as usize] = index;
         }
 
-        let mut next_state = self.clone();
-        if next_state.make_move(Move::Down) == true {
-            next_states.push(next_state);
+        let order: Vec<usize> = self
+            .board
+            .iter()
+            .filter(|&&tile| tile != 0)
+            .map(|&tile| target_index[tile as usize])
+            .collect();
+
+        let mut count = 0;
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                if order[i] > order[j] {
+                    count += 1;
+                }
+            }
         }
+        count
+    }
 
-        let mut next_state = self.clone();
-        if next_state.make_move(Move::Right) == true {
-            next_states.push(next_state);
+    // A horizontal move never changes the tile order. A vertical move jumps a
+    // tile over `width - 1` others, which flips the inversion parity only when
+    // the width is even, and it also moves the blank by one row. So on odd
+    // widths the inversion parity is invariant, and on even widths the parity
+    // of inversions plus the blank's row distance from its target row is.
+    pub fn is_solvable(&self) -> bool {
+        if self.width == 1 || self.height == 1 {
+            let board_order = self.board.iter().filter(|&&tile| tile != 0);
+            let target_order = self.target.iter().filter(|&&tile| tile != 0);
+            return board_order.eq(target_order);
         }
 
-        self.children = next_states.clone();
-        if reverse {
-            return self.children.iter_mut().rev().collect();
-        } else {
-            return self.children.iter_mut().collect();
+        let mut parity = self.inversions();
+
+        if self.width.is_multiple_of(2) {
+            let blank_row = self.blank_position() / self.width;
+            let target_blank_row = self.target.iter().position(|&x| x == 0).unwrap() / self.width;
+            parity += blank_row.abs_diff(target_blank_row);
         }
+
+        parity.is_multiple_of(2)
     }
 
     fn is_game_complete(&self) -> bool {
         self.board == self.target
     }
 
-    fn make_move(&mut self, kind: Move) -> bool {
+    fn make_move(&mut self, kind: &Move) -> bool {
         let blank_position = self.blank_position();
-        let swap_position: i8 = match kind {
-            Move::Up => blank_position as i8 - 3,
-            Move::Down => blank_position as i8 + 3,
-            Move::Left => {
-                if blank_position == 0 || blank_position == 3 || blank_position == 6 {
-                    -1
-                } else {
-                    blank_position as i8 - 1
-                }
-            }
-            Move::Right => {
-                if blank_position == 2 || blank_position == 5 || blank_position == 8 {
-                    -1
-                } else {
-                    blank_position as i8 + 1
-                }
-            }
+        let column = blank_position % self.width;
+        let row = blank_position / self.width;
+
+        let swap_position = match kind {
+            Move::Up if row > 0 => Some(blank_position - self.width),
+            Move::Down if row + 1 < self.height => Some(blank_position + self.width),
+            Move::Left if column > 0 => Some(blank_position - 1),
+            Move::Right if column + 1 < self.width => Some(blank_position + 1),
+            _ => None,
         };
 
-        if swap_position >= 0 && swap_position < 9 {
-            self.board[blank_position] = self.board[swap_position as usize];
-            self.board[swap_position as usize] = 0;
-            self.move_history.push(kind);
+        if let Some(swap_position) = swap_position {
+            self.board[blank_position] = self.board[swap_position];
+            self.board[swap_position] = 0;
             return true;
         }
 
         return false;
     }
 
-    pub fn build_tree_depth_first(
-        visualize: bool,
-        limit: usize,
-        state: [u8; 9],
-        target: [u8; 9],
-    ) -> (Vec<String>, EightPuzzleState) {
-        let mut game = EightPuzzleState::new(visualize, state, target);
-        let mut graphviz = vec![String::new()];
-
-        let mut stack: Vec<(&mut EightPuzzleState, usize)> = Vec::new();
-        let mut visited: HashSet<u64> = HashSet::new();
-
-        if visualize {
-            graphviz.last_mut().unwrap().push_str(&format!(
-                "    {} [label=\"{}\", fillcolor=blue, style=filled];\n",
-                game.repr(),
-                game.to_string(),
-            ));
-        }
+    // A puzzle that is ready to be searched, rejecting targets that cannot be
+    // reached from `state`.
+    pub fn start(state: Board, target: Board) -> Result<EightPuzzleState, PuzzleError> {
+        let (width, height) = (state.width(), state.height());
 
-        stack.push((&mut game, 0));
+        if (target.width(), target.height()) != (width, height) {
+            return Err(PuzzleError::SizeMismatch {
+                start: (width, height),
+                target: (target.width(), target.height()),
+            });
+        }
 
-        if visualize {
-            graphviz.push(graphviz.last().unwrap().clone());
+        let game = EightPuzzleState::new(width, height, state.into_tiles(), target.into_tiles());
+        if !game.is_solvable() {
+            return Err(PuzzleError::Unsolvable);
         }
 
-        let mut current_state;
-        let mut depth;
-        let mut neighbours;
+        Ok(game)
+    }
 
-        let mut found = false;
-        while let Some((state, d)) = stack.pop() {
-            current_state = state;
-            depth = d;
+    // Depth-first search that does not expand nodes deeper than `limit`.
+    pub fn depth_limited_search(
+        &self,
+        limit: usize,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> (SearchResult, DepthLimitedOutcome) {
+        let (result, outcome) = search::depth_limited_search(self, limit, observer);
 
-            if depth > limit {
-                continue;
-            }
+        return (boards(result), outcome);
+    }
 
-            let current_state_clone = current_state.clone();
+    pub fn iterative_deepening_search(
+        &self,
+        max_limit: usize,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> (SearchResult, Vec<DeepeningIteration>) {
+        let (result, iterations) = search::iterative_deepening_search(self, max_limit, observer);
 
-            visited.insert(current_state.int_repr());
+        return (boards(result), iterations);
+    }
 
-            if current_state.is_game_complete() {
-                println!("{:?}", current_state);
-                break;
-            }
+    pub fn astar_search(
+        &self,
+        heuristic_fn: impl Fn(&EightPuzzleState) -> usize,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> SearchResult {
+        return boards(search::astar_search(self, heuristic_fn, observer));
+    }
 
-            neighbours = current_state.discover_neighbours(false);
-            neighbours.reverse();
+    pub fn ida_star_search(
+        &self,
+        heuristic_fn: impl Fn(&EightPuzzleState) -> usize,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> (SearchResult, Vec<IdaStarIteration>) {
+        let (result, iterations) = search::ida_star_search(self, heuristic_fn, observer);
 
-            for neighbour in neighbours {
-                let neighbour_clone = neighbour.clone();
+        return (boards(result), iterations);
+    }
 
-                if visited.contains(&neighbour.int_repr()) {
-                    graphviz.push(graphviz.last().unwrap().clone());
-                    continue;
-                } else {
-                    stack.push((neighbour, depth + 1));
-                }
+    pub fn uniform_cost_search(
+        &self,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> SearchResult {
+        return boards(search::uniform_cost_search(self, observer));
+    }
 
-                let color = if neighbour_clone.is_game_complete() {
-                    "green"
-                } else {
-                    "white"
-                };
-
-                if visualize {
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} [label=\"{}\", fillcolor={}, style=filled];\n",
-                        neighbour_clone.repr(),
-                        neighbour_clone.to_string(),
-                        color,
-                    ));
-
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} -> {} [label=\"{:?}\", color=red];\n",
-                        current_state_clone.repr(),
-                        neighbour_clone.repr(),
-                        neighbour_clone.move_history.last().unwrap()
-                    ));
-
-                    graphviz.push(graphviz.last().unwrap().clone());
-                }
+    pub fn build_tree_depth_first(
+        &self,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> (SearchResult, Tree) {
+        return board_tree(search::depth_first_tree(self, observer));
+    }
 
-                if neighbour_clone.is_game_complete() {
-                    found = true;
-                    break;
-                }
-            }
-            if found {
-                break;
-            }
-        }
+    pub fn build_tree_breadth_first(
+        &self,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> (SearchResult, Tree) {
+        return board_tree(search::breadth_first_tree(self, observer));
+    }
 
-        return (
-            graphviz
-                .iter()
-                .map(|x| format!("digraph {{\n{}}}", x))
-                .collect(),
-            game,
-        );
+    // Breadth-first search over ranked boards, for boards of up to
+    // `ranking::MAX_TILES` tiles.
+    pub fn ranked_breadth_first(
+        &self,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> Result<SearchResult, RankError> {
+        return Ok(RankedTree::search(self, false, observer)?.into_result());
     }
 
-    pub fn astar_evaluation(&self, heuristic_fn: fn(&EightPuzzleState) -> usize) -> usize {
-        self.move_history.len() + heuristic_fn(&self)
+    pub fn bidirectional_breadth_first(
+        &self,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> SearchResult {
+        return boards(search::bidirectional_breadth_first_search(self, observer));
     }
+}
 
-    pub fn astar_search(
-        visualize: bool,
-        heuristic_fn: fn(&EightPuzzleState) -> usize,
-        state: [u8; 9],
-        target: [u8; 9],
-    ) -> (Vec<String>, EightPuzzleState) {
-        let mut game = EightPuzzleState::new(visualize, state, target);
-        let mut graphviz = vec![String::new()];
-
-        let mut queue: Vec<&mut EightPuzzleState> = Vec::new();
-        let mut visited: HashSet<u64> = HashSet::new();
-
-        if visualize {
-            graphviz.last_mut().unwrap().push_str(&format!(
-                "    {} [label=\"{}\", fillcolor=blue, style=filled];\n",
-                game.repr(),
-                format!(
-                    "{}\\n\\nh={}",
-                    game.to_string(),
-                    game.astar_evaluation(heuristic_fn)
-                ),
-            ));
-
-            graphviz.push(graphviz.last().unwrap().clone());
-        }
+// The puzzle is its own start state, every successor shares its dimensions
+// and target.
+impl Problem for EightPuzzleState {
+    type State = EightPuzzleState;
+    type Action = Move;
+    type Key = Vec<u8>;
 
-        queue.push(&mut game);
+    fn initial_state(&self) -> EightPuzzleState {
+        self.clone()
+    }
 
-        let mut current_state;
-        let mut neighbours;
-        let mut found = false;
+    fn successors(&self, state: &EightPuzzleState) -> Vec<(Move, EightPuzzleState, usize)> {
+        let mut next_states = Vec::new();
 
-        while queue.len() > 0 {
-            current_state = queue.remove(0);
+        for kind in [Move::Left, Move::Up, Move::Down, Move::Right] {
+            let mut next_state = state.clone();
+            if next_state.make_move(&kind) {
+                next_states.push((kind, next_state, 1));
+            }
+        }
 
-            let current_state_clone = current_state.clone();
+        next_states
+    }
 
-            visited.insert(current_state.int_repr());
+    fn is_goal(&self, state: &EightPuzzleState) -> bool {
+        state.is_game_complete()
+    }
 
-            neighbours = current_state.discover_neighbours(false);
+    fn key(&self, state: &EightPuzzleState) -> Vec<u8> {
+        state.board.clone()
+    }
 
-            for neighbour in neighbours {
-                let neighbour_clone = neighbour.clone();
+    fn label(&self, state: &EightPuzzleState) -> String {
+        state.to_string()
+    }
 
-                if visited.contains(&neighbour.int_repr()) {
-                    if visualize {
-                        graphviz.push(graphviz.last().unwrap().clone());
-                    }
-                    continue;
-                } else if queue.contains(&neighbour) {
-                    if visualize {
-                        graphviz.push(graphviz.last().unwrap().clone());
-                    }
-                    continue;
-                } else {
-                    queue.push(neighbour);
-                }
+    fn state_bytes(&self, state: &EightPuzzleState) -> usize {
+        size_of::<EightPuzzleState>() + state.board.len() + state.target.len()
+    }
+}
 
-                if visualize {
-                    let color = if neighbour_clone.is_game_complete() {
-                        "green"
-                    } else {
-                        "white"
-                    };
-
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} [label=\"{}\", fillcolor={}, style=filled];\n",
-                        neighbour_clone.repr(),
-                        format!(
-                            "{}\\n\\nh={}",
-                            neighbour_clone.to_string(),
-                            neighbour_clone.astar_evaluation(heuristic_fn),
-                        ),
-                        color,
-                    ));
-
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} -> {} [label=\"{:?}\", color=red];\n",
-                        current_state_clone.repr(),
-                        neighbour_clone.repr(),
-                        neighbour_clone.move_history.last().unwrap()
-                    ));
-
-                    graphviz.push(graphviz.last().unwrap().clone());
-                }
+impl ReversibleProblem for EightPuzzleState {
+    fn goal_state(&self) -> EightPuzzleState {
+        EightPuzzleState {
+            board: self.target.clone(),
+            ..self.clone()
+        }
+    }
 
-                if neighbour_clone.is_game_complete() {
-                    found = true;
-                    break;
-                }
-            }
+    // Every move can be undone, so the predecessors are the successors with
+    // the move that leads back.
+    fn predecessors(&self, state: &EightPuzzleState) -> Vec<(Move, EightPuzzleState, usize)> {
+        self.successors(state)
+            .into_iter()
+            .map(|(kind, previous, cost)| (kind.inverse(), previous, cost))
+            .collect()
+    }
+}
 
-            if found {
-                break;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::graphviz::Graphviz;
+
+    fn start(
+        width: usize,
+        height: usize,
+        state: Vec<u8>,
+        target: Vec<u8>,
+    ) -> Result<EightPuzzleState, PuzzleError> {
+        EightPuzzleState::start(
+            Board::new(width, height, state).unwrap(),
+            Board::new(width, height, target).unwrap(),
+        )
+    }
 
-            // Reorder queue based on heuristic_fn
-            queue.sort_by(|a, b| {
-                let a_heuristic = a.astar_evaluation(heuristic_fn);
-                let b_heuristic = b.astar_evaluation(heuristic_fn);
+    #[test]
+    fn test_build_tree() {
+        let game = start(
+            3,
+            3,
+            vec![1, 2, 3, 0, 4, 6, 7, 5, 8],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 0],
+        )
+        .unwrap();
 
-                a_heuristic.cmp(&b_heuristic)
-            });
-        }
+        let mut graphviz = Graphviz::new(&game);
+        let result = game.astar_search(EightPuzzleState::manhattan_distance, &mut graphviz);
+        assert_eq!(result.cost, 3);
+
+        // A single frame list, with the goal marked in the last frame.
+        let deltas = graphviz.into_deltas();
+        assert_eq!(deltas.len(), 1);
+        assert!(deltas[0].last().unwrap().contains("fillcolor=green"));
+    }
+
+    #[test]
+    fn test_rectangular_and_larger_boards() {
+        let state = EightPuzzleState::new(
+            4,
+            2,
+            vec![1, 2, 3, 4, 5, 6, 7, 0],
+            vec![1, 2, 3, 4, 5, 6, 7, 0],
+        );
+        assert_eq!(state.manhattan_distance(), 0);
 
-        return (
-            graphviz
-                .iter()
-                .map(|x| format!("digraph {{\n{}}}", x))
-                .collect(),
-            game,
+        let (result, tree) = start(
+            4,
+            2,
+            vec![1, 2, 3, 4, 5, 0, 6, 7],
+            vec![1, 2, 3, 4, 5, 6, 7, 0],
+        )
+        .unwrap()
+        .build_tree_breadth_first(&mut ());
+        assert!(result.found);
+        assert_eq!(result.actions.len(), 2);
+        assert_eq!(result.states.last().unwrap(), &vec![1, 2, 3, 4, 5, 6, 7, 0]);
+
+        // The first move reaches the three neighbours of the blank, and the
+        // goal is found among the children of one of them.
+        let goal = tree.len() - 1;
+        assert!(tree.nodes()[1..4].iter().all(|node| node.parent == Some(0)));
+        assert_eq!(tree.ancestors(goal).count(), 3);
+        assert_eq!(tree.path_to(goal).1, result.states);
+
+        let fifteen = EightPuzzleState::new(
+            4,
+            4,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 0, 15],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0],
         );
+        assert_eq!(fifteen.manhattan_distance(), 1);
+        assert_eq!(fifteen.misplaced_tiles(), 1);
+        assert_eq!(fifteen.key(&fifteen), fifteen.board);
     }
 
-    pub fn build_tree_breadth_first(
-        visualize: bool,
-        state: [u8; 9],
-        target: [u8; 9],
-    ) -> (Vec<String>, EightPuzzleState) {
-        let mut game = EightPuzzleState::new(visualize, state, target);
-        let mut graphviz = vec![String::new()];
-
-        let mut queue: Vec<&mut EightPuzzleState> = Vec::new();
-        let mut visited: HashSet<u64> = HashSet::new();
-
-        if visualize {
-            graphviz.last_mut().unwrap().push_str(&format!(
-                "    {} [label=\"{}\", fillcolor=blue, style=filled];\n",
-                game.repr(),
-                game.to_string(),
-            ));
-
-            graphviz.push(graphviz.last().unwrap().clone());
-        }
+    #[test]
+    fn test_unsolvable_boards_are_rejected() {
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
+
+        let swapped = EightPuzzleState::new(3, 3, vec![2, 1, 3, 4, 5, 6, 7, 8, 0], target.clone());
+        assert!(!swapped.is_solvable());
+
+        let result = start(3, 3, vec![2, 1, 3, 4, 5, 6, 7, 8, 0], target);
+        assert_eq!(result.unwrap_err(), PuzzleError::Unsolvable);
+
+        let result =
+            EightPuzzleState::start("123/456/780".parse().unwrap(), "1234/5670".parse().unwrap());
+        assert_eq!(
+            result.unwrap_err(),
+            PuzzleError::SizeMismatch {
+                start: (3, 3),
+                target: (4, 2)
+            }
+        );
 
-        queue.push(&mut game);
+        // On even widths the blank's row matters as well as the inversions.
+        let fifteen_target = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0];
+        let one_up = EightPuzzleState::new(
+            4,
+            4,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0, 13, 14, 15, 12],
+            fifteen_target.clone(),
+        );
+        assert!(one_up.is_solvable());
+
+        // Sam Loyd's 14-15 puzzle.
+        let loyd = EightPuzzleState::new(
+            4,
+            4,
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 15, 14, 0],
+            fifteen_target,
+        );
+        assert!(!loyd.is_solvable());
 
-        let mut current_state;
-        let mut neighbours;
-        let mut found = false;
+        // Other boards of a puzzle are checked as well, but need not be
+        // solvable.
+        let game = EightPuzzleState::start(
+            "123/456/708".parse().unwrap(),
+            "123/456/780".parse().unwrap(),
+        )
+        .unwrap();
+        assert!(!game
+            .with_board(vec![2, 1, 3, 4, 5, 6, 7, 8, 0])
+            .unwrap()
+            .is_solvable());
+        assert_eq!(
+            game.with_board(vec![1, 2, 3, 4, 5, 6, 7, 8, 8])
+                .unwrap_err(),
+            BoardError::DuplicateTile {
+                tile: 8,
+                missing: 0
+            }
+        );
+        assert_eq!(
+            game.with_board(vec![1, 2, 3, 0]).unwrap_err(),
+            BoardError::SizeMismatch {
+                expected: 9,
+                found: 4
+            }
+        );
+    }
 
-        while queue.len() > 0 {
-            current_state = queue.remove(0);
+    #[test]
+    fn test_astar_is_optimal() {
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
+
+        for (board, optimal) in [
+            (vec![1, 2, 3, 0, 4, 6, 7, 5, 8], 3),
+            (vec![6, 0, 8, 3, 1, 5, 2, 7, 4], 21),
+            (vec![8, 6, 7, 2, 5, 4, 3, 0, 1], 31),
+        ] {
+            for heuristic_fn in [
+                EightPuzzleState::manhattan_distance,
+                EightPuzzleState::misplaced_tiles,
+                EightPuzzleState::linear_conflict,
+            ] {
+                let result = start(3, 3, board.clone(), target.clone())
+                    .unwrap()
+                    .astar_search(heuristic_fn, &mut ());
+
+                assert!(result.found);
+                assert_eq!(result.cost, optimal);
+                assert_eq!(result.states.last(), Some(&target));
+            }
+        }
+    }
 
-            let current_state_clone = current_state.clone();
+    #[test]
+    fn test_heuristic_dominance() {
+        assert_eq!(longest_increasing_subsequence(&[2, 0, 3, 1]), 2);
+
+        // Three reversed tiles in their goal row, two of which have to step
+        // out of it, and two swapped tiles in another.
+        let reversed = start(
+            3,
+            3,
+            vec![3, 2, 1, 4, 5, 6, 8, 7, 0],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 0],
+        )
+        .unwrap();
+        assert_eq!(reversed.manhattan_distance(), 6);
+        assert_eq!(reversed.linear_conflict(), 12);
 
-            visited.insert(current_state.int_repr());
+        // A target other than the usual one, where the blank ends up in the
+        // middle.
+        let target = vec![1, 2, 3, 8, 0, 4, 7, 6, 5];
+        let game = start(3, 3, vec![5, 6, 7, 4, 0, 8, 3, 2, 1], target.clone()).unwrap();
 
-            neighbours = current_state.discover_neighbours(false);
+        let heuristics = [
+            EightPuzzleState::misplaced_tiles,
+            EightPuzzleState::manhattan_distance,
+            EightPuzzleState::linear_conflict,
+        ];
+        let results: Vec<SearchResult> = heuristics
+            .iter()
+            .map(|&heuristic_fn| game.astar_search(heuristic_fn, &mut ()))
+            .collect();
+
+        for (weaker, stronger) in results.iter().zip(&results[1..]) {
+            assert_eq!(weaker.cost, stronger.cost);
+            assert!(weaker.stats.nodes_expanded >= stronger.stats.nodes_expanded);
+        }
 
-            for neighbour in neighbours {
-                // println!("{:?}", visited.len());
+        let result = &results[0];
+        for (depth, board) in result.states.iter().enumerate() {
+            let state = start(3, 3, board.clone(), target.clone()).unwrap();
+            let estimates = heuristics.map(|heuristic_fn| heuristic_fn(&state));
 
-                let neighbour_clone = neighbour.clone();
+            assert!(estimates.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(estimates[2] <= result.cost - depth);
+        }
+    }
 
-                if visited.contains(&neighbour.int_repr()) {
-                    if visualize {
-                        graphviz.push(graphviz.last().unwrap().clone());
-                    }
-                    continue;
-                } else if queue.contains(&neighbour) {
-                    if visualize {
-                        graphviz.push(graphviz.last().unwrap().clone());
-                    }
-                    continue;
-                } else {
-                    queue.push(neighbour);
-                }
+    #[test]
+    fn test_ida_star_grows_threshold_to_optimal_cost() {
+        let game = start(
+            3,
+            3,
+            vec![6, 0, 8, 3, 1, 5, 2, 7, 4],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 0],
+        )
+        .unwrap();
+
+        let mut graphviz = Graphviz::new(&game);
+        let (result, iterations) =
+            game.ida_star_search(EightPuzzleState::manhattan_distance, &mut graphviz);
+        let deltas = graphviz.into_deltas();
+
+        assert!(result.found);
+        assert_eq!(result.cost, 21);
+        assert_eq!(iterations.last().unwrap().threshold, 21);
+        assert!(iterations
+            .windows(2)
+            .all(|pair| pair[0].threshold < pair[1].threshold));
+        assert_eq!(deltas.len(), iterations.len());
+        assert!(deltas.iter().all(|deltas| !deltas.is_empty()));
+    }
 
-                if visualize {
-                    let color = if neighbour_clone.is_game_complete() {
-                        "green"
-                    } else {
-                        "white"
-                    };
-
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} [label=\"{}\", fillcolor={}, style=filled];\n",
-                        neighbour_clone.repr(),
-                        neighbour_clone.to_string(),
-                        color,
-                    ));
-
-                    graphviz.last_mut().unwrap().push_str(&format!(
-                        "    {} -> {} [label=\"{:?}\", color=red];\n",
-                        current_state_clone.repr(),
-                        neighbour_clone.repr(),
-                        neighbour_clone.move_history.last().unwrap()
-                    ));
-
-                    graphviz.push(graphviz.last().unwrap().clone());
-                }
+    #[test]
+    fn test_depth_limited_search_outcomes() {
+        let board = vec![1, 2, 3, 0, 4, 6, 7, 5, 8];
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
 
-                if neighbour_clone.is_game_complete() {
-                    println!("{:?}", neighbour_clone);
-                    found = true;
-                    break;
-                }
-            }
+        let game = start(3, 3, board, target).unwrap();
 
-            if found {
-                break;
-            }
-        }
+        let (_, outcome) = game.depth_limited_search(2, &mut ());
+        assert_eq!(outcome, DepthLimitedOutcome::Cutoff);
 
-        // println!("{:?}", graphviz);
+        let (result, outcome) = game.depth_limited_search(3, &mut ());
+        assert_eq!(outcome, DepthLimitedOutcome::Solution);
+        assert_eq!(result.actions.len(), 3);
 
-        return (
-            graphviz
-                .iter()
-                .map(|x| format!("digraph {{\n{}}}", x))
-                .collect(),
-            game,
+        let (result, iterations) = game.iterative_deepening_search(10, &mut ());
+        assert_eq!(iterations.len(), 4);
+        assert_eq!(
+            iterations.last().unwrap().outcome,
+            DepthLimitedOutcome::Solution
         );
+        assert_eq!(result.cost, 3);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_build_tree() {
-        // let test = EightPuzzleState {
-        //     board: [0, 2, 3, 1, 4, 6, 7, 5, 8],
-        //     target: [1, 2, 3, 4, 5, 6, 7, 8, 0],
-        //     move_history: Vec::new(),
-        //     children: Vec::new(),
-        //     graphviz: false,
-        // };
-        //
-        // println!("Manhattan: {:?}", test.manhattan_distance());
-        // return;
-
-        let (ret, _) = EightPuzzleState::astar_search(
-            true,
-            EightPuzzleState::manhattan_distance,
-            [1, 2, 3, 0, 4, 6, 7, 5, 8],
-            [1, 2, 3, 4, 5, 6, 7, 8, 0],
-        );
-
-        println!("{:?}", ret);
+    fn test_bidirectional_breadth_first_finds_shortest_path() {
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
+
+        for (board, optimal) in [
+            (vec![1, 2, 3, 4, 5, 6, 7, 8, 0], 0),
+            (vec![1, 2, 3, 0, 4, 6, 7, 5, 8], 3),
+            (vec![6, 0, 8, 3, 1, 5, 2, 7, 4], 21),
+        ] {
+            let game = start(3, 3, board, target.clone()).unwrap();
+
+            let mut graphviz = Graphviz::new(&game);
+            let result = game.bidirectional_breadth_first(&mut graphviz);
+            assert!(!graphviz.into_deltas().is_empty());
+
+            assert!(result.found);
+            assert_eq!(result.cost, optimal);
+            assert_eq!(result.states.last(), Some(&target));
+        }
+    }
 
-        // let (ret, _) = EightPuzzleState::build_tree_breadth_first(
-        //
-        //     false,
-        //     [6, 0, 8, 3, 1, 5, 2, 7, 4],
-        //     [1, 2, 3, 4, 5, 6, 7, 8, 0],
-        // );
-        //
-        // println!("{:?}", ret);
+    #[test]
+    fn test_search_stats() {
+        let result = start(
+            3,
+            3,
+            vec![6, 0, 8, 3, 1, 5, 2, 7, 4],
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 0],
+        )
+        .unwrap()
+        .astar_search(EightPuzzleState::manhattan_distance, &mut ());
+
+        let stats = result.stats;
+        assert_eq!(stats.solution_depth, Some(21));
+        assert!(stats.nodes_generated >= stats.nodes_expanded);
+        assert!(stats.max_frontier > 0 && stats.peak_memory_bytes > 0);
+        assert!(stats.effective_branching_factor.unwrap() > 1.0);
     }
 }
@@ -0,0 +1,340 @@
+use std::fmt::Display;
+
+use search::Problem;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    eight_puzzle::EightPuzzleState,
+    ranking::{self, RankError},
+    state_space::StateSpace,
+};
+
+// SplitMix64, which is small and gives the same sequence for a seed on every
+// platform and build.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // A number below `bound`, which must not be zero. The slight bias of the
+    // modulo does not matter for picking boards.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scramble {
+    // Distinct boards whose optimal solution takes exactly this many moves,
+    // drawn uniformly among all such boards. Limited to boards of up to ten
+    // tiles.
+    Distance(usize),
+    // Boards reached by this many random moves of the blank from the target,
+    // never undoing the previous move unless it is the only one, as at the
+    // end of a single row. Works for boards of any size, and leaves a single
+    // cell, where the blank cannot move, at the target.
+    RandomWalk(usize),
+}
+
+// Everything a set of boards was generated from, so that it can be generated
+// again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorParameters {
+    pub seed: u64,
+    pub count: usize,
+    pub scramble: Scramble,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratedBoard {
+    pub board: Vec<u8>,
+    // Optimal number of moves to the target, only known for boards of up to
+    // ten tiles.
+    pub distance: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generated {
+    pub width: usize,
+    pub height: usize,
+    pub target: Vec<u8>,
+    pub parameters: GeneratorParameters,
+    pub boards: Vec<GeneratedBoard>,
+}
+
+impl Generated {
+    // The generated boards as puzzles, ready to be searched.
+    pub fn games(&self, game: &EightPuzzleState) -> Vec<EightPuzzleState> {
+        self.boards
+            .iter()
+            .map(|generated| {
+                game.with_board(generated.board.clone())
+                    .expect("generated boards hold the tiles of the target")
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneratorError {
+    Rank(RankError),
+    // Fewer distinct boards than were asked for are that far from the target.
+    NotEnoughBoards { distance: usize, available: usize },
+}
+
+impl Display for GeneratorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeneratorError::Rank(error) => error.fmt(f),
+            GeneratorError::NotEnoughBoards {
+                distance,
+                available,
+            } => write!(
+                f,
+                "only {} boards are {} moves away from the target",
+                available, distance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+impl From<RankError> for GeneratorError {
+    fn from(error: RankError) -> GeneratorError {
+        GeneratorError::Rank(error)
+    }
+}
+
+// Generates solvable boards for the target of `game`. The same parameters
+// always give the same boards.
+pub fn generate(
+    game: &EightPuzzleState,
+    parameters: &GeneratorParameters,
+) -> Result<Generated, GeneratorError> {
+    let mut random = Random::new(parameters.seed);
+
+    let boards = match parameters.scramble {
+        Scramble::Distance(distance) => {
+            let space = StateSpace::explore(game)?;
+            let mut candidates: Vec<usize> = space
+                .distances()
+                .filter(|&(_, candidate)| candidate == distance)
+                .map(|(rank, _)| rank)
+                .collect();
+            if candidates.len() < parameters.count {
+                return Err(GeneratorError::NotEnoughBoards {
+                    distance,
+                    available: candidates.len(),
+                });
+            }
+
+            // The first steps of a Fisher-Yates shuffle.
+            (0..parameters.count)
+                .map(|i| {
+                    let picked = i + random.below(candidates.len() - i);
+                    candidates.swap(i, picked);
+                    GeneratedBoard {
+                        board: ranking::unrank(candidates[i], space.tiles()),
+                        distance: Some(distance),
+                    }
+                })
+                .collect()
+        }
+        Scramble::RandomWalk(moves) => {
+            let boards: Vec<Vec<u8>> = (0..parameters.count)
+                .map(|_| random_walk(game, moves, &mut random))
+                .collect();
+
+            // Distances are looked up afterwards, for boards small enough to
+            // explore.
+            let space = match ranking::check(game.board().len()) {
+                Ok(()) => Some(StateSpace::explore(game)?),
+                Err(_) => None,
+            };
+            boards
+                .into_iter()
+                .map(|board| GeneratedBoard {
+                    distance: space.as_ref().and_then(|space| space.distance(&board)),
+                    board,
+                })
+                .collect()
+        }
+    };
+
+    Ok(Generated {
+        width: game.width(),
+        height: game.height(),
+        target: game.target().to_vec(),
+        parameters: parameters.clone(),
+        boards,
+    })
+}
+
+fn random_walk(game: &EightPuzzleState, moves: usize, random: &mut Random) -> Vec<u8> {
+    let mut state = game.at_target();
+    let mut previous: Option<Vec<u8>> = None;
+
+    for _ in 0..moves {
+        let mut successors: Vec<EightPuzzleState> = game
+            .successors(&state)
+            .into_iter()
+            .map(|(_, next, _)| next)
+            .collect();
+        if successors.is_empty() {
+            break;
+        } else if successors.len() > 1 {
+            successors.retain(|next| Some(next.board()) != previous.as_deref());
+        }
+
+        let next = successors.swap_remove(random.below(successors.len()));
+        previous = Some(state.board().to_vec());
+        state = next;
+    }
+
+    state.board().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(target: &str) -> EightPuzzleState {
+        EightPuzzleState::start(target.parse().unwrap(), target.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_boards_at_distance() {
+        let game = goal("123/456/780");
+        let parameters = GeneratorParameters {
+            seed: 7,
+            count: 20,
+            scramble: Scramble::Distance(18),
+        };
+
+        let generated = generate(&game, &parameters).unwrap();
+        assert_eq!(generated.parameters, parameters);
+        assert_eq!(generated.boards.len(), 20);
+
+        let mut boards: Vec<&Vec<u8>> = generated
+            .boards
+            .iter()
+            .map(|generated| &generated.board)
+            .collect();
+        boards.sort();
+        boards.dedup();
+        assert_eq!(boards.len(), 20);
+
+        for puzzle in generated.games(&game).iter().take(5) {
+            assert!(puzzle.is_solvable());
+            let result = puzzle.astar_search(EightPuzzleState::manhattan_distance, &mut ());
+            assert_eq!(result.cost, 18);
+        }
+
+        // The same parameters give the same boards, another seed others.
+        let again = generate(&game, &parameters).unwrap();
+        assert_eq!(again.boards, generated.boards);
+        let other = generate(
+            &game,
+            &GeneratorParameters {
+                seed: 8,
+                ..parameters
+            },
+        )
+        .unwrap();
+        assert_ne!(other.boards, generated.boards);
+
+        let error = generate(
+            &game,
+            &GeneratorParameters {
+                count: 3,
+                scramble: Scramble::Distance(31),
+                ..parameters
+            },
+        );
+        assert_eq!(
+            error.err(),
+            Some(GeneratorError::NotEnoughBoards {
+                distance: 31,
+                available: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_random_walks() {
+        let parameters = GeneratorParameters {
+            seed: 42,
+            count: 5,
+            scramble: Scramble::RandomWalk(40),
+        };
+
+        // Every move of the blank changes the parity of the distance.
+        let game = goal("12/34/50");
+        let generated = generate(&game, &parameters).unwrap();
+        for board in &generated.boards {
+            let distance = board.distance.unwrap();
+            assert!(distance <= 40 && distance % 2 == 0);
+        }
+
+        // Larger boards are scrambled, but their distance is not known.
+        let game = goal("1 2 3 4 / 5 6 7 8 / 9 10 11 12 / 13 14 15 0");
+        let generated = generate(&game, &parameters).unwrap();
+        assert_eq!(generated.boards.len(), 5);
+        for puzzle in generated.games(&game) {
+            assert!(puzzle.is_solvable());
+            assert_ne!(puzzle.board(), game.target());
+        }
+        assert!(generated
+            .boards
+            .iter()
+            .all(|board| board.distance.is_none()));
+        assert_eq!(
+            generate(&game, &parameters).unwrap().boards,
+            generated.boards
+        );
+
+        // The blank bounces back from the ends of a single row.
+        let row = || vec![vec![1, 2, 0]].try_into().unwrap();
+        let game = EightPuzzleState::start(row(), row()).unwrap();
+        let generated = generate(
+            &game,
+            &GeneratorParameters {
+                scramble: Scramble::RandomWalk(3),
+                ..parameters
+            },
+        )
+        .unwrap();
+        assert!(generated
+            .boards
+            .iter()
+            .all(|board| board.board == [1, 0, 2] && board.distance == Some(1)));
+
+        // A single cell has no moves at all.
+        let game = goal("0");
+        let generated = generate(
+            &game,
+            &GeneratorParameters {
+                scramble: Scramble::RandomWalk(3),
+                ..parameters
+            },
+        )
+        .unwrap();
+        assert!(generated
+            .boards
+            .iter()
+            .all(|board| board.board == [0] && board.distance == Some(0)));
+    }
+}
@@ -0,0 +1,334 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    eight_puzzle::EightPuzzleState,
+    ranking::{self, RankError},
+    state_space::StateSpace,
+};
+
+// Violations of each kind kept as examples per heuristic. All of them are
+// counted.
+pub const MAX_EXAMPLES: usize = 20;
+
+// A board the heuristic overestimates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Overestimate {
+    pub board: Vec<u8>,
+    pub h: usize,
+    pub distance: usize,
+}
+
+// A move along which the heuristic drops by more than the cost of the move.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inconsistency {
+    pub from: Vec<u8>,
+    pub to: Vec<u8>,
+    pub h_from: usize,
+    pub h_to: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeuristicReport {
+    pub name: String,
+    // Never above the optimal solution length.
+    pub admissible: bool,
+    // Never dropping by more than one along a move.
+    pub consistent: bool,
+    pub overestimated: usize,
+    pub inconsistent_moves: usize,
+    // Boards where the heuristic is the optimal solution length.
+    pub exact: usize,
+    // Errors are distances from the optimal solution length, in either
+    // direction.
+    pub mean_error: f64,
+    pub max_error: usize,
+    pub mean_h: f64,
+    pub overestimates: Vec<Overestimate>,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relation {
+    Equal,
+    Dominates,
+    DominatedBy,
+    Incomparable,
+}
+
+// How the first heuristic compares to the second on every board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dominance {
+    pub first: String,
+    pub second: String,
+    pub relation: Relation,
+    // Boards where the first is larger and where the second is.
+    pub first_larger: usize,
+    pub second_larger: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Analysis {
+    pub states: usize,
+    pub moves: usize,
+    pub reports: Vec<HeuristicReport>,
+    // Every pair of heuristics, in the order they were given.
+    pub dominance: Vec<Dominance>,
+    pub elapsed_ms: f64,
+}
+
+// A heuristic and the name it is reported under.
+pub type NamedHeuristic<'a> = (&'a str, &'a dyn Fn(&EightPuzzleState) -> usize);
+
+// Checks heuristics against the exact distances of every board that can
+// reach a target, so any function that could be handed to `astar_search` can
+// be graded.
+pub struct HeuristicAnalyzer {
+    goal: EightPuzzleState,
+    space: StateSpace,
+}
+
+impl HeuristicAnalyzer {
+    // Explores the state space of the target of `game`, which takes a moment
+    // for ten tiles.
+    pub fn new(game: &EightPuzzleState) -> Result<HeuristicAnalyzer, RankError> {
+        Ok(HeuristicAnalyzer {
+            goal: game.at_target(),
+            space: StateSpace::explore(game)?,
+        })
+    }
+
+    pub fn space(&self) -> &StateSpace {
+        &self.space
+    }
+
+    pub fn analyze(&self, heuristics: &[NamedHeuristic<'_>]) -> Analysis {
+        let started = Instant::now();
+        let (width, tiles) = (self.space.width(), self.space.tiles());
+        let distances: Vec<(usize, usize)> = self.space.distances().collect();
+
+        // Every heuristic is evaluated once per board and looked up by rank
+        // when checking moves.
+        let permutations: usize = (1..=tiles).product();
+        let mut values = vec![vec![0; permutations]; heuristics.len()];
+        for &(rank, _) in &distances {
+            let state = self
+                .goal
+                .with_board(ranking::unrank(rank, tiles))
+                .expect("ranks unrank to boards of the same tiles");
+            for (h, (_, heuristic_fn)) in values.iter_mut().zip(heuristics) {
+                h[rank] = heuristic_fn(&state);
+            }
+        }
+
+        let mut reports: Vec<HeuristicReport> = heuristics
+            .iter()
+            .map(|(name, _)| HeuristicReport {
+                name: name.to_string(),
+                admissible: true,
+                consistent: true,
+                overestimated: 0,
+                inconsistent_moves: 0,
+                exact: 0,
+                mean_error: 0.0,
+                max_error: 0,
+                mean_h: 0.0,
+                overestimates: Vec::new(),
+                inconsistencies: Vec::new(),
+            })
+            .collect();
+        let mut moves = 0;
+
+        for &(rank, distance) in &distances {
+            let board = ranking::unrank(rank, tiles);
+            let neighbours = ranking::neighbours(&board, width);
+            moves += neighbours.len();
+
+            for (report, h) in reports.iter_mut().zip(&values) {
+                let value = h[rank];
+                report.mean_error += value.abs_diff(distance) as f64;
+                report.max_error = report.max_error.max(value.abs_diff(distance));
+                report.mean_h += value as f64;
+
+                if value == distance {
+                    report.exact += 1;
+                } else if value > distance {
+                    report.overestimated += 1;
+                    if report.overestimates.len() < MAX_EXAMPLES {
+                        report.overestimates.push(Overestimate {
+                            board: board.clone(),
+                            h: value,
+                            distance,
+                        });
+                    }
+                }
+
+                for &next in &neighbours {
+                    if value > h[next] + 1 {
+                        report.inconsistent_moves += 1;
+                        if report.inconsistencies.len() < MAX_EXAMPLES {
+                            report.inconsistencies.push(Inconsistency {
+                                from: board.clone(),
+                                to: ranking::unrank(next, tiles),
+                                h_from: value,
+                                h_to: h[next],
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        for report in &mut reports {
+            report.admissible = report.overestimated == 0;
+            report.consistent = report.inconsistent_moves == 0;
+            report.mean_error /= distances.len() as f64;
+            report.mean_h /= distances.len() as f64;
+        }
+
+        let mut dominance = Vec::new();
+        for first in 0..heuristics.len() {
+            for second in first + 1..heuristics.len() {
+                dominance.push(compare(
+                    &distances,
+                    (heuristics[first].0, &values[first]),
+                    (heuristics[second].0, &values[second]),
+                ));
+            }
+        }
+
+        Analysis {
+            states: distances.len(),
+            moves,
+            reports,
+            dominance,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+fn compare(
+    distances: &[(usize, usize)],
+    (first, first_values): (&str, &[usize]),
+    (second, second_values): (&str, &[usize]),
+) -> Dominance {
+    let (mut first_larger, mut second_larger) = (0, 0);
+    for &(rank, _) in distances {
+        if first_values[rank] > second_values[rank] {
+            first_larger += 1;
+        } else if first_values[rank] < second_values[rank] {
+            second_larger += 1;
+        }
+    }
+
+    let relation = match (first_larger, second_larger) {
+        (0, 0) => Relation::Equal,
+        (_, 0) => Relation::Dominates,
+        (0, _) => Relation::DominatedBy,
+        _ => Relation::Incomparable,
+    };
+
+    Dominance {
+        first: first.to_string(),
+        second: second.to_string(),
+        relation,
+        first_larger,
+        second_larger,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walking_distance::WalkingDistance;
+
+    #[test]
+    fn test_heuristic_quality() {
+        let game = EightPuzzleState::start(
+            "867/254/301".parse().unwrap(),
+            "123/456/780".parse().unwrap(),
+        )
+        .unwrap();
+        let analyzer = HeuristicAnalyzer::new(&game).unwrap();
+        let walking_distance = WalkingDistance::new(3, 3, game.target());
+
+        let manhattan = EightPuzzleState::manhattan_distance;
+        let misplaced = EightPuzzleState::misplaced_tiles;
+        let linear_conflict = EightPuzzleState::linear_conflict;
+        let walking = |state: &EightPuzzleState| walking_distance.distance(state.board());
+        // Admissible, but it forgets everything once the blank leaves the
+        // corner.
+        let corner = |state: &EightPuzzleState| match state.blank_position() {
+            8 => state.manhattan_distance(),
+            _ => 0,
+        };
+        let doubled = |state: &EightPuzzleState| 2 * state.manhattan_distance();
+
+        let analysis = analyzer.analyze(&[
+            ("manhattan", &manhattan),
+            ("misplaced", &misplaced),
+            ("linear conflict", &linear_conflict),
+            ("walking distance", &walking),
+            ("corner", &corner),
+            ("doubled", &doubled),
+        ]);
+        assert_eq!(analysis.states, 181440);
+        assert_eq!(analysis.moves, 181440 * 24 / 9);
+
+        for report in &analysis.reports[..4] {
+            assert!(report.admissible && report.consistent, "{}", report.name);
+            assert!(report.overestimates.is_empty() && report.inconsistencies.is_empty());
+            assert!(report.max_error <= 31 && report.mean_error > 0.0);
+        }
+        let mean_errors: Vec<f64> = analysis.reports[..3]
+            .iter()
+            .map(|report| report.mean_error)
+            .collect();
+        assert!(mean_errors[2] < mean_errors[0] && mean_errors[0] < mean_errors[1]);
+
+        let corner = &analysis.reports[4];
+        assert!(corner.admissible && !corner.consistent);
+        assert_eq!(corner.inconsistencies.len(), MAX_EXAMPLES);
+        let example = &corner.inconsistencies[0];
+        assert!(example.h_from > example.h_to + 1);
+
+        let doubled = &analysis.reports[5];
+        assert!(!doubled.admissible);
+        assert_eq!(doubled.overestimates.len(), MAX_EXAMPLES);
+        for overestimate in &doubled.overestimates {
+            let state = game.with_board(overestimate.board.clone()).unwrap();
+            assert_eq!(overestimate.h, 2 * state.manhattan_distance());
+            assert_eq!(
+                analyzer.space().distance(&overestimate.board),
+                Some(overestimate.distance)
+            );
+            assert!(overestimate.h > overestimate.distance);
+        }
+
+        let relation = |first: &str, second: &str| {
+            analysis
+                .dominance
+                .iter()
+                .find(|dominance| dominance.first == first && dominance.second == second)
+                .map(|dominance| dominance.relation)
+        };
+        assert_eq!(
+            relation("manhattan", "misplaced"),
+            Some(Relation::Dominates)
+        );
+        assert_eq!(
+            relation("manhattan", "linear conflict"),
+            Some(Relation::DominatedBy)
+        );
+        assert_eq!(
+            relation("manhattan", "doubled"),
+            Some(Relation::DominatedBy)
+        );
+        assert_eq!(
+            relation("linear conflict", "walking distance"),
+            Some(Relation::Incomparable)
+        );
+        assert_eq!(analysis.dominance.len(), 15);
+    }
+}
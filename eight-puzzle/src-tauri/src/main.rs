@@ -1,32 +1,139 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod board;
+mod commands;
 mod eight_puzzle;
+mod generator;
+mod heuristic_quality;
+mod pattern_database;
+mod ranking;
+mod state_space;
+mod walking_distance;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// The search running in the background, if any. Only one runs at a time.
+#[derive(Default)]
+struct RunningSearch {
+    cancel: Arc<AtomicBool>,
+    // Bumped for every search, so that one which has been replaced stops
+    // reporting to the window.
+    latest: Arc<AtomicUsize>,
+}
+
+impl RunningSearch {
+    // Asks the search to stop without waiting for it. It reports what it
+    // found so far once it has.
+    fn stop(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+struct Search(Mutex<RunningSearch>);
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
+//
+// Starts the search on a background thread. Progress is pushed to the window
+// as `search-update` events, and the result as `search-finished`, or the
+// error as `search-failed` if its pattern database could not be built or the
+// search panicked.
 #[tauri::command]
-fn generate_tree_breadth() -> Vec<String> {
-    let (ret, _) = eight_puzzle::EightPuzzleState::build_tree_depth_first(
-        true,
-        8,
-        [1, 2, 3, 8, 0, 4, 7, 6, 5],
-        [8, 0, 3, 2, 6, 4, 1, 7, 5],
-    );
+fn start_search(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    search: tauri::State<Search>,
+    request: commands::SolveRequest,
+) -> Result<(), commands::SolveError> {
+    let prepared = request.prepare(app.path_resolver().app_cache_dir())?;
+
+    let mut running = search.0.lock().unwrap();
+    running.stop();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    running.cancel = cancel.clone();
+    let id = running.latest.fetch_add(1, Ordering::SeqCst) + 1;
+    let latest = running.latest.clone();
+    let current = move || latest.load(Ordering::SeqCst) == id;
+
+    let worker_window = window.clone();
+    let worker_current = current.clone();
+    let worker = thread::spawn(move || {
+        let response = prepared.run(&cancel, |update| {
+            if worker_current() {
+                let _ = worker_window.emit("search-update", update);
+            }
+        });
+
+        if worker_current() {
+            let _ = match response {
+                Ok(response) => worker_window.emit("search-finished", response),
+                Err(error) => worker_window.emit("search-failed", error),
+            };
+        }
+    });
 
-    return ret;
+    // Waits for the search off the main thread, so that one which panics is
+    // still reported.
+    thread::spawn(move || {
+        if worker.join().is_err() && current() {
+            let _ = window.emit("search-failed", commands::SolveError::Panicked);
+        }
+    });
+
+    return Ok(());
 }
 
-// #[tauri::command]
-// fn generate_tree_depth() -> Vec<String> {
-//     return eight_puzzle::EightPuzzleState::build_tree_depth_first(
-//         [2, 8, 3, 1, 6, 4, 7, 0, 5],
-//         [8, 0, 3, 2, 6, 4, 1, 7, 5],
-//     );
-// }
+// Stops the running search. Its partial result, or the full result if it had
+// already finished, arrives as `search-finished`.
+#[tauri::command]
+fn cancel_search(search: tauri::State<Search>) {
+    search.0.lock().unwrap().stop();
+}
+
+// Runs an exhaustive search from the goal, reporting how far every board is
+// from it.
+#[tauri::command(async)]
+fn analyze_target(goal: board::BoardInput) -> Result<state_space::Summary, commands::SolveError> {
+    return commands::analyze_target(goal);
+}
+
+// Checks the heuristics on every board that can reach the goal.
+#[tauri::command(async)]
+fn analyze_heuristics(
+    app: tauri::AppHandle,
+    goal: board::BoardInput,
+    heuristics: Vec<commands::Heuristic>,
+) -> Result<heuristic_quality::Analysis, commands::SolveError> {
+    let cache_dir = app.path_resolver().app_cache_dir();
+    return commands::analyze_heuristics(goal, heuristics, cache_dir);
+}
+
+// Generates boards for the goal, recording the seed so that they can be
+// generated again.
+#[tauri::command(async)]
+fn generate_boards(
+    request: commands::GenerateRequest,
+) -> Result<generator::Generated, commands::SolveError> {
+    return request.run();
+}
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![generate_tree_breadth])
-        // .invoke_handler(tauri::generate_handler![generate_tree_depth])
+        .manage(Search::default())
+        .invoke_handler(tauri::generate_handler![
+            start_search,
+            cancel_search,
+            analyze_target,
+            analyze_heuristics,
+            generate_boards
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,653 @@
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+const MAGIC: &[u8; 4] = b"EPDB";
+// Bump whenever the file layout or the meaning of the tables changes, so that
+// stale caches are rebuilt instead of misread.
+const VERSION: u32 = 1;
+
+const UNREACHED: u8 = u8::MAX;
+
+// Positions are ranked with a 64 bit mask of the occupied cells.
+const MAX_CELLS: usize = 64;
+
+// Building a table needs a bit for every arrangement of the pattern tiles and
+// the blank, and queues that hold an arrangement once for every way it is
+// reached. A six-tile group of the 15-puzzle has 58 million arrangements and
+// peaks at about 280 MB, taking half a minute in a release build. A seven-tile
+// group would need nine times as much, so this stops at six.
+const MAX_SEARCH_BITS: u64 = 1 << 26;
+
+// The tile groups used when none are given: chunks of six tiles in the order
+// of their numbers, which is the 6-6-3 partition for the 15-puzzle.
+const DEFAULT_GROUP_SIZE: usize = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatternError {
+    BoardTooLarge { tiles: usize },
+    // A tile that is not on the board, the blank, or a tile in two groups.
+    InvalidTile(u8),
+    EmptyGroup,
+    // Building the table of this group would take too much memory.
+    TooLarge { group: usize, arrangements: u64 },
+    Io(String),
+    // The file is not a pattern database, or it is truncated or damaged.
+    Corrupt,
+    UnsupportedVersion(u32),
+    // The build was stopped before it finished.
+    Cancelled,
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternError::BoardTooLarge { tiles } => write!(
+                f,
+                "pattern databases support boards of up to {} tiles, not {}",
+                MAX_CELLS, tiles
+            ),
+            PatternError::InvalidTile(tile) => {
+                write!(f, "tile {} cannot be part of a pattern", tile)
+            }
+            PatternError::EmptyGroup => write!(f, "pattern groups cannot be empty"),
+            PatternError::TooLarge {
+                group,
+                arrangements,
+            } => write!(
+                f,
+                "pattern {} has {} arrangements, which is too many to build",
+                group + 1,
+                arrangements
+            ),
+            PatternError::Io(error) => write!(f, "{}", error),
+            PatternError::Corrupt => write!(f, "the pattern database file is damaged"),
+            PatternError::UnsupportedVersion(version) => {
+                write!(f, "pattern database version {} is not supported", version)
+            }
+            PatternError::Cancelled => {
+                write!(f, "the pattern database was cancelled before it was built")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<io::Error> for PatternError {
+    fn from(error: io::Error) -> PatternError {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            PatternError::Corrupt
+        } else {
+            PatternError::Io(error.to_string())
+        }
+    }
+}
+
+// Number of ways to place `count` distinct tokens on `cells` cells, or
+// `u64::MAX` if there are more.
+fn arrangements(cells: usize, count: usize) -> u64 {
+    (0..count)
+        .map(|i| (cells - i) as u64)
+        .fold(1, u64::saturating_mul)
+}
+
+// Index of an arrangement of distinct cells among all `arrangements(cells,
+// positions.len())` of them. Every position is counted among the cells not
+// taken by the ones before it.
+fn rank(positions: &[u8], cells: usize) -> u64 {
+    let mut used = 0u64;
+    let mut rank = 0u64;
+
+    for (i, &position) in positions.iter().enumerate() {
+        let below = used & ((1u64 << position) - 1);
+        let free_below = position as u64 - below.count_ones() as u64;
+        rank = rank * (cells - i) as u64 + free_below;
+        used |= 1 << position;
+    }
+
+    rank
+}
+
+// Moves counted for one group of tiles, indexed by the rank of their
+// positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    tiles: Vec<u8>,
+    table: Vec<u8>,
+}
+
+impl Pattern {
+    // Retrograde breadth-first search from the target, over the positions of
+    // the pattern tiles and the blank. Only moves of pattern tiles are
+    // counted, so that the tables of disjoint groups can be added up, and
+    // every entry keeps the fewest moves over all positions of the blank.
+    fn build(
+        width: usize,
+        height: usize,
+        target: &[u8],
+        tiles: Vec<u8>,
+        cancel: &AtomicBool,
+    ) -> Result<Pattern, PatternError> {
+        let cells = width * height;
+        let tokens = tiles.len() + 1;
+
+        let mut table = vec![UNREACHED; arrangements(cells, tiles.len()) as usize];
+        let mut visited = vec![0u64; arrangements(cells, tokens).div_ceil(64) as usize];
+
+        // Arrangements are queued as the positions of the tiles followed by
+        // the blank, one level per number of moves. Moving only the blank is
+        // free, so those successors join the current level. States are marked
+        // when they are expanded rather than queued, so that a state queued
+        // for the next level is still expanded at the current one if it turns
+        // out to be free to reach.
+        let mut level: Vec<u8> = tiles
+            .iter()
+            .chain([&0])
+            .map(|&tile| target.iter().position(|&t| t == tile).unwrap() as u8)
+            .collect();
+        let mut moves = 0u8;
+
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            let mut index = 0;
+
+            while index < level.len() {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(PatternError::Cancelled);
+                }
+
+                let mut positions = level[index..index + tokens].to_vec();
+                index += tokens;
+
+                let state = rank(&positions, cells) as usize;
+                if visited[state / 64] & (1 << (state % 64)) != 0 {
+                    continue;
+                }
+                visited[state / 64] |= 1 << (state % 64);
+
+                let placement = rank(&positions[..tokens - 1], cells) as usize;
+                if table[placement] == UNREACHED {
+                    table[placement] = moves;
+                }
+
+                let blank = positions[tokens - 1] as usize;
+                let (x, y) = (blank % width, blank / width);
+                let neighbours = [
+                    (x > 0).then(|| blank - 1),
+                    (x + 1 < width).then(|| blank + 1),
+                    (y > 0).then(|| blank - width),
+                    (y + 1 < height).then(|| blank + width),
+                ];
+
+                for neighbour in neighbours.into_iter().flatten() {
+                    let neighbour = neighbour as u8;
+                    let moved = positions[..tokens - 1]
+                        .iter()
+                        .position(|&position| position == neighbour);
+
+                    positions[tokens - 1] = neighbour;
+                    match moved {
+                        Some(tile) => {
+                            positions[tile] = blank as u8;
+                            next.extend_from_slice(&positions);
+                            positions[tile] = neighbour;
+                        }
+                        None => level.extend_from_slice(&positions),
+                    }
+                    positions[tokens - 1] = blank as u8;
+                }
+            }
+
+            level = next;
+            moves = moves.saturating_add(1).min(UNREACHED - 1);
+        }
+
+        Ok(Pattern { tiles, table })
+    }
+
+    fn distance(&self, tile_positions: &[u8], cells: usize) -> usize {
+        let positions: Vec<u8> = self
+            .tiles
+            .iter()
+            .map(|&tile| tile_positions[tile as usize])
+            .collect();
+
+        self.table[rank(&positions, cells) as usize] as usize
+    }
+}
+
+// What a cached database has to have been built for.
+struct Layout<'a> {
+    width: usize,
+    height: usize,
+    target: &'a [u8],
+    groups: &'a [Vec<u8>],
+}
+
+// An additive heuristic made of one table per disjoint group of tiles. It is
+// built for a single board shape and target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternDatabase {
+    width: usize,
+    height: usize,
+    target: Vec<u8>,
+    patterns: Vec<Pattern>,
+}
+
+impl PatternDatabase {
+    pub fn default_groups(tiles: usize) -> Vec<Vec<u8>> {
+        let numbered: Vec<u8> = (1..tiles).map(|tile| tile as u8).collect();
+        numbered
+            .chunks(DEFAULT_GROUP_SIZE)
+            .map(|group| group.to_vec())
+            .collect()
+    }
+
+    // Checks that the groups are disjoint, leave out the blank and are small
+    // enough to be built. Tiles in no group do not count towards the
+    // heuristic.
+    pub fn check(width: usize, height: usize, groups: &[Vec<u8>]) -> Result<(), PatternError> {
+        let cells = width * height;
+
+        if cells > MAX_CELLS {
+            return Err(PatternError::BoardTooLarge { tiles: cells });
+        }
+
+        let mut seen = vec![false; cells];
+
+        for (index, group) in groups.iter().enumerate() {
+            if group.is_empty() {
+                return Err(PatternError::EmptyGroup);
+            }
+
+            for &tile in group {
+                if tile == 0 || tile as usize >= cells || seen[tile as usize] {
+                    return Err(PatternError::InvalidTile(tile));
+                }
+                seen[tile as usize] = true;
+            }
+
+            let arrangements = arrangements(cells, (group.len() + 1).min(cells));
+            if arrangements > MAX_SEARCH_BITS {
+                return Err(PatternError::TooLarge {
+                    group: index,
+                    arrangements,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Builds every table, or stops with `PatternError::Cancelled` soon after
+    // `cancel` is set.
+    pub fn build(
+        width: usize,
+        height: usize,
+        target: &[u8],
+        groups: &[Vec<u8>],
+        cancel: &AtomicBool,
+    ) -> Result<PatternDatabase, PatternError> {
+        PatternDatabase::check(width, height, groups)?;
+
+        Ok(PatternDatabase {
+            width,
+            height,
+            target: target.to_vec(),
+            patterns: groups
+                .iter()
+                .map(|group| Pattern::build(width, height, target, group.clone(), cancel))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    // A lower bound on the moves from `board` to the target.
+    pub fn distance(&self, board: &[u8]) -> usize {
+        let cells = self.width * self.height;
+
+        let mut tile_positions = vec![0; cells];
+        for (position, &tile) in board.iter().enumerate() {
+            tile_positions[tile as usize] = position as u8;
+        }
+
+        self.patterns
+            .iter()
+            .map(|pattern| pattern.distance(&tile_positions, cells))
+            .sum()
+    }
+
+    // Layout, little-endian: magic, version (u32), width and height (u16),
+    // the target, the number of patterns (u16), then for every pattern its
+    // size (u16), its tiles and its table.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.width as u16).to_le_bytes())?;
+        writer.write_all(&(self.height as u16).to_le_bytes())?;
+        writer.write_all(&self.target)?;
+        writer.write_all(&(self.patterns.len() as u16).to_le_bytes())?;
+
+        for pattern in &self.patterns {
+            writer.write_all(&(pattern.tiles.len() as u16).to_le_bytes())?;
+            writer.write_all(&pattern.tiles)?;
+            writer.write_all(&pattern.table)?;
+        }
+
+        writer.flush()
+    }
+
+    pub fn read_from(reader: impl Read) -> Result<PatternDatabase, PatternError> {
+        PatternDatabase::read(reader, None)
+    }
+
+    // Reads a database, checking every header before the table it announces
+    // is read. With `expected`, a file for any other board, target or groups
+    // is treated as damaged.
+    fn read(
+        mut reader: impl Read,
+        expected: Option<&Layout<'_>>,
+    ) -> Result<PatternDatabase, PatternError> {
+        fn bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+            let mut bytes = [0; N];
+            reader.read_exact(&mut bytes)?;
+            Ok(bytes)
+        }
+
+        // Grows the buffer as bytes arrive, so that a truncated file does not
+        // allocate the whole length up front.
+        fn vec(reader: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+            let mut bytes = Vec::new();
+            reader.take(len as u64).read_to_end(&mut bytes)?;
+            if bytes.len() < len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            Ok(bytes)
+        }
+
+        if &bytes::<4>(&mut reader)? != MAGIC {
+            return Err(PatternError::Corrupt);
+        }
+
+        let version = u32::from_le_bytes(bytes(&mut reader)?);
+        if version != VERSION {
+            return Err(PatternError::UnsupportedVersion(version));
+        }
+
+        let width = u16::from_le_bytes(bytes(&mut reader)?) as usize;
+        let height = u16::from_le_bytes(bytes(&mut reader)?) as usize;
+        let cells = width * height;
+        if cells > MAX_CELLS
+            || expected.is_some_and(|layout| (layout.width, layout.height) != (width, height))
+        {
+            return Err(PatternError::Corrupt);
+        }
+
+        let target = vec(&mut reader, cells)?;
+        let count = u16::from_le_bytes(bytes(&mut reader)?) as usize;
+        if count >= cells
+            || expected
+                .is_some_and(|layout| layout.target != target || layout.groups.len() != count)
+        {
+            return Err(PatternError::Corrupt);
+        }
+
+        let mut groups = Vec::new();
+        let mut tables = Vec::new();
+        for index in 0..count {
+            let size = u16::from_le_bytes(bytes(&mut reader)?) as usize;
+            if size >= cells {
+                return Err(PatternError::Corrupt);
+            }
+
+            // The groups so far must be valid and small enough to have been
+            // built before the size of the table is trusted.
+            groups.push(vec(&mut reader, size)?);
+            if PatternDatabase::check(width, height, &groups).is_err()
+                || expected.is_some_and(|layout| layout.groups[index] != groups[index])
+            {
+                return Err(PatternError::Corrupt);
+            }
+
+            tables.push(vec(&mut reader, arrangements(cells, size) as usize)?);
+        }
+
+        Ok(PatternDatabase {
+            width,
+            height,
+            target,
+            patterns: groups
+                .into_iter()
+                .zip(tables)
+                .map(|(tiles, table)| Pattern { tiles, table })
+                .collect(),
+        })
+    }
+
+    // Files are named after everything the tables depend on, so databases
+    // for different boards can share a directory.
+    fn cache_path(
+        dir: &Path,
+        width: usize,
+        height: usize,
+        target: &[u8],
+        groups: &[Vec<u8>],
+    ) -> PathBuf {
+        // FNV-1a, which unlike the standard hasher is stable across builds.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in target.iter().chain(
+            groups
+                .iter()
+                .flat_map(|group| group.iter().chain([&u8::MAX])),
+        ) {
+            hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+
+        dir.join(format!("pattern-{}x{}-{:016x}.pdb", width, height, hash))
+    }
+
+    // Loads the database from `dir`, or builds and saves it there if it is
+    // missing, outdated, damaged or made for another board. Failing to save
+    // only means it will be built again next time.
+    pub fn load_or_build(
+        dir: &Path,
+        width: usize,
+        height: usize,
+        target: &[u8],
+        groups: &[Vec<u8>],
+        cancel: &AtomicBool,
+    ) -> Result<PatternDatabase, PatternError> {
+        let path = PatternDatabase::cache_path(dir, width, height, target, groups);
+        let layout = Layout {
+            width,
+            height,
+            target,
+            groups,
+        };
+
+        let cached = File::open(&path)
+            .map_err(PatternError::from)
+            .and_then(|file| PatternDatabase::read(BufReader::new(file), Some(&layout)));
+        if let Ok(database) = cached {
+            return Ok(database);
+        }
+
+        let database = PatternDatabase::build(width, height, target, groups, cancel)?;
+        let _ = fs::create_dir_all(dir)
+            .and_then(|_| File::create(&path))
+            .and_then(|file| database.write_to(BufWriter::new(file)));
+
+        Ok(database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eight_puzzle::EightPuzzleState;
+
+    #[test]
+    fn test_rank() {
+        let mut ranks: Vec<u64> = Vec::new();
+        for a in 0..4u8 {
+            for b in (0..4u8).filter(|&b| b != a) {
+                ranks.push(rank(&[a, b], 4));
+            }
+        }
+
+        assert_eq!(ranks, (0..arrangements(4, 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_pattern_database() {
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
+        let groups = PatternDatabase::default_groups(9);
+        assert_eq!(groups, [vec![1, 2, 3, 4, 5, 6], vec![7, 8]]);
+
+        let database =
+            PatternDatabase::build(3, 3, &target, &groups, &AtomicBool::new(false)).unwrap();
+        assert_eq!(database.distance(&target), 0);
+
+        // Admissible, and never weaker than the Manhattan distance, on every
+        // state of an optimal solution.
+        let game = EightPuzzleState::start(
+            "867/254/301".parse().unwrap(),
+            "123/456/780".parse().unwrap(),
+        )
+        .unwrap();
+        let result = game.astar_search(|state| database.distance(state.board()), &mut ());
+        assert_eq!(result.cost, 31);
+
+        for (depth, board) in result.states.iter().enumerate() {
+            let manhattan = EightPuzzleState::start(
+                board.clone().try_into().unwrap(),
+                target.clone().try_into().unwrap(),
+            )
+            .unwrap()
+            .manhattan_distance();
+            let distance = database.distance(board);
+
+            assert!(distance <= result.cost - depth);
+            assert!(distance >= manhattan);
+        }
+
+        let manhattan = game.astar_search(EightPuzzleState::manhattan_distance, &mut ());
+        assert!(result.stats.nodes_expanded < manhattan.stats.nodes_expanded);
+    }
+
+    #[test]
+    fn test_database_files() {
+        let target = vec![1, 2, 3, 4, 5, 6, 7, 8, 0];
+        let groups = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+        let cancel = AtomicBool::new(false);
+        let database = PatternDatabase::build(3, 3, &target, &groups, &cancel).unwrap();
+
+        let mut bytes = Vec::new();
+        database.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4 + 4 + 4 + 9 + 2 + 2 * (2 + 4 + 3024));
+        assert_eq!(PatternDatabase::read_from(&bytes[..]), Ok(database.clone()));
+
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..100]),
+            Err(PatternError::Corrupt)
+        );
+        bytes[4] = 2;
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..]),
+            Err(PatternError::UnsupportedVersion(2))
+        );
+
+        let dir = std::env::temp_dir().join(format!("pattern-database-{}", std::process::id()));
+        let built = PatternDatabase::load_or_build(&dir, 3, 3, &target, &groups, &cancel).unwrap();
+        let loaded = PatternDatabase::load_or_build(&dir, 3, 3, &target, &groups, &cancel).unwrap();
+        assert_eq!(built, database);
+        assert_eq!(loaded, database);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // Damaged caches and caches for another target are rebuilt.
+        let path = PatternDatabase::cache_path(&dir, 3, 3, &target, &groups);
+        let mut other = Vec::new();
+        PatternDatabase::build(3, 3, &[0, 1, 2, 3, 4, 5, 6, 7, 8], &groups, &cancel)
+            .unwrap()
+            .write_to(&mut other)
+            .unwrap();
+        for damaged in [&bytes[..100], &other[..]] {
+            fs::write(&path, damaged).unwrap();
+            let rebuilt =
+                PatternDatabase::load_or_build(&dir, 3, 3, &target, &groups, &cancel).unwrap();
+            assert_eq!(rebuilt, database);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            PatternDatabase::check(3, 3, &[vec![1, 2], vec![2, 3]]),
+            Err(PatternError::InvalidTile(2))
+        );
+        assert_eq!(
+            PatternDatabase::check(3, 3, &[vec![0, 1]]),
+            Err(PatternError::InvalidTile(0))
+        );
+        assert!(matches!(
+            PatternDatabase::check(4, 4, &[(1..8).collect()]),
+            Err(PatternError::TooLarge { group: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_headers() {
+        // An 8x8 board with one group of 40 tiles, whose table would take
+        // about 10^68 bytes, and no table after it.
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&8u16.to_le_bytes());
+        bytes.extend_from_slice(&8u16.to_le_bytes());
+        bytes.extend((1..64).chain([0]));
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&40u16.to_le_bytes());
+        bytes.extend(1..=40);
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..]),
+            Err(PatternError::Corrupt)
+        );
+
+        // A valid group with its table cut short.
+        let size = 4 + 4 + 4 + 64 + 2;
+        bytes.truncate(size);
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend([1, 2, 3]);
+        bytes.extend([0; 1000]);
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..]),
+            Err(PatternError::Corrupt)
+        );
+
+        // Sizes past the board and boards past 64 cells.
+        bytes.truncate(size);
+        bytes.extend_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..]),
+            Err(PatternError::Corrupt)
+        );
+        bytes[8..10].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert_eq!(
+            PatternDatabase::read_from(&bytes[..]),
+            Err(PatternError::Corrupt)
+        );
+    }
+
+    #[test]
+    fn test_cancelled_build() {
+        let target: Vec<u8> = (1..16).chain([0]).collect();
+        assert_eq!(
+            PatternDatabase::build(4, 4, &target, &[vec![1, 2]], &AtomicBool::new(true)),
+            Err(PatternError::Cancelled)
+        );
+    }
+}
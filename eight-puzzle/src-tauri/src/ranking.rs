@@ -0,0 +1,412 @@
+use std::{collections::VecDeque, fmt::Display, mem::size_of, time::Instant};
+
+use search::{effective_branching_factor, SearchEvent, SearchObserver, SearchStats};
+use serde::{Deserialize, Serialize};
+
+use crate::eight_puzzle::{EightPuzzleState, Move, SearchResult};
+
+// Searches over ranks keep a bit and a parent for every permutation of the
+// tiles, which is 3.6 million of each for ten tiles and 479 million for the
+// twelve of a 3x4 board.
+pub const MAX_TILES: usize = 10;
+
+const UNREACHED: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RankError {
+    TooManyTiles { tiles: usize },
+}
+
+impl Display for RankError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RankError::TooManyTiles { tiles } => write!(
+                f,
+                "boards of {} tiles have too many permutations to rank, at most {} are supported",
+                tiles, MAX_TILES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RankError {}
+
+pub fn check(tiles: usize) -> Result<(), RankError> {
+    if tiles > MAX_TILES {
+        return Err(RankError::TooManyTiles { tiles });
+    }
+
+    Ok(())
+}
+
+// Position of `board` among all permutations of its tiles in lexicographic
+// order, from its Lehmer code: every tile counts the smaller tiles that come
+// after it, i.e. that are not yet used.
+pub fn rank(board: &[u8]) -> usize {
+    let mut used = 0u64;
+    let mut rank = 0;
+
+    for (i, &tile) in board.iter().enumerate() {
+        let smaller_used = (used & ((1u64 << tile) - 1)).count_ones() as usize;
+        rank = rank * (board.len() - i) + tile as usize - smaller_used;
+        used |= 1 << tile;
+    }
+
+    rank
+}
+
+// The board of `tiles` tiles with the given rank.
+pub fn unrank(mut rank: usize, tiles: usize) -> Vec<u8> {
+    let mut digits = vec![0; tiles];
+    for i in (0..tiles).rev() {
+        digits[i] = rank % (tiles - i);
+        rank /= tiles - i;
+    }
+
+    let mut unused: Vec<u8> = (0..tiles as u8).collect();
+    digits
+        .into_iter()
+        .map(|digit| unused.remove(digit))
+        .collect()
+}
+
+// Ranks of the boards one move of the blank away from `board`.
+pub fn neighbours(board: &[u8], width: usize) -> Vec<usize> {
+    let height = board.len() / width;
+    let blank = board.iter().position(|&tile| tile == 0).unwrap();
+    let (x, y) = (blank % width, blank / width);
+
+    let mut board = board.to_vec();
+    [
+        (x > 0).then(|| blank - 1),
+        (y > 0).then(|| blank - width),
+        (y + 1 < height).then(|| blank + width),
+        (x + 1 < width).then(|| blank + 1),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|neighbour| {
+        board.swap(blank, neighbour);
+        let next = rank(&board);
+        board.swap(blank, neighbour);
+        next
+    })
+    .collect()
+}
+
+// The move of the blank that turns `from` into `to`.
+fn move_between(from: &[u8], to: &[u8], width: usize) -> Move {
+    let blank = |board: &[u8]| board.iter().position(|&tile| tile == 0).unwrap();
+    let (before, after) = (blank(from), blank(to));
+
+    match after as isize - before as isize {
+        -1 => Move::Left,
+        1 => Move::Right,
+        offset if offset < 0 => Move::Up,
+        _ => {
+            debug_assert_eq!(after, before + width);
+            Move::Down
+        }
+    }
+}
+
+// Breadth-first search tree over ranked boards. A bitset marks the boards
+// that have been reached, the parent of each is stored by rank, and the
+// frontier holds ranks rather than states.
+pub struct RankedTree {
+    width: usize,
+    tiles: usize,
+    root: usize,
+    visited: Vec<u64>,
+    // Rank of the board every reached board was first generated from. The
+    // root is its own parent.
+    parents: Vec<u32>,
+    reached: usize,
+    goal: Option<usize>,
+    interrupted: bool,
+    stats: SearchStats,
+}
+
+impl RankedTree {
+    // Searches from the start board until the target is generated or, if
+    // `exhaustive`, until every board reachable from the start has been.
+    // Nodes are numbered in the order their boards are first reached, and
+    // states are only built to report them. Boards reached again are counted
+    // but not reported, since the number of the node that reached them first
+    // is not kept.
+    pub fn search(
+        game: &EightPuzzleState,
+        exhaustive: bool,
+        observer: &mut impl SearchObserver<Move, EightPuzzleState>,
+    ) -> Result<RankedTree, RankError> {
+        let started = Instant::now();
+        let width = game.width();
+        let tiles = game.board().len();
+        check(tiles)?;
+
+        let permutations: usize = (1..=tiles).product();
+        let root = rank(game.board());
+        let target = rank(game.target());
+
+        let mut tree = RankedTree {
+            width,
+            tiles,
+            root,
+            visited: vec![0; permutations.div_ceil(64)],
+            parents: vec![UNREACHED; permutations],
+            reached: 0,
+            goal: None,
+            interrupted: false,
+            stats: SearchStats::default(),
+        };
+        tree.reach(root, root);
+        observer.event(SearchEvent::NodeGenerated {
+            id: 0,
+            parent: None,
+            state: game,
+            depth: 0,
+            g: 0,
+            h: None,
+            backward: false,
+        });
+
+        // Ranks of the boards to expand, with the numbers of their nodes and
+        // their depths.
+        let mut frontier = VecDeque::from([(root as u32, 0u32, 0u32)]);
+        if root == target {
+            tree.goal = Some(root);
+            frontier.clear();
+        }
+
+        while let Some((current, id, depth)) = frontier.pop_front() {
+            if observer.interrupted() {
+                tree.interrupted = true;
+                break;
+            }
+
+            observer.event(SearchEvent::NodeExpanded { id: id as usize });
+            tree.stats.nodes_expanded += 1;
+
+            let board = unrank(current as usize, tiles);
+
+            for next in neighbours(&board, width) {
+                tree.stats.nodes_generated += 1;
+
+                if tree.contains_rank(next) {
+                    tree.stats.duplicates_pruned += 1;
+                    continue;
+                }
+
+                let next_id = tree.reached as u32;
+                tree.reach(next, current as usize);
+
+                let next_board = unrank(next, tiles);
+                observer.event(SearchEvent::NodeGenerated {
+                    id: next_id as usize,
+                    parent: Some((id as usize, &move_between(&board, &next_board, width))),
+                    state: &game
+                        .with_board(next_board)
+                        .expect("neighbours keep the tiles of the board"),
+                    depth: depth as usize + 1,
+                    g: depth as usize + 1,
+                    h: None,
+                    backward: false,
+                });
+                if next == target && tree.goal.is_none() {
+                    tree.goal = Some(next);
+                    if !exhaustive {
+                        frontier.clear();
+                        break;
+                    }
+                }
+
+                frontier.push_back((next as u32, next_id, depth + 1));
+            }
+
+            tree.stats.max_frontier = tree.stats.max_frontier.max(frontier.len());
+            observer.event(SearchEvent::FrontierSnapshot {
+                frontier: frontier.len(),
+                stored: tree.reached,
+            });
+        }
+
+        let depth = tree.goal.map(|goal| tree.depth_of(goal));
+        let stats = &mut tree.stats;
+        stats.peak_memory_bytes = tree.visited.len() * size_of::<u64>()
+            + tree.parents.len() * size_of::<u32>()
+            + stats.max_frontier * size_of::<(u32, u32, u32)>();
+        stats.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        stats.solution_depth = depth;
+        stats.effective_branching_factor =
+            depth.and_then(|depth| effective_branching_factor(stats.nodes_generated, depth));
+
+        Ok(tree)
+    }
+
+    fn reach(&mut self, rank: usize, parent: usize) {
+        self.visited[rank / 64] |= 1 << (rank % 64);
+        self.parents[rank] = parent as u32;
+        self.reached += 1;
+    }
+
+    fn contains_rank(&self, rank: usize) -> bool {
+        self.visited[rank / 64] & (1 << (rank % 64)) != 0
+    }
+
+    fn depth_of(&self, mut rank: usize) -> usize {
+        let mut depth = 0;
+        while rank != self.root {
+            rank = self.parents[rank] as usize;
+            depth += 1;
+        }
+        depth
+    }
+
+    // Number of boards reached, including the start.
+    pub fn reached(&self) -> usize {
+        self.reached
+    }
+
+    pub fn contains(&self, board: &[u8]) -> bool {
+        board.len() == self.tiles && self.contains_rank(rank(board))
+    }
+
+    // Moves and boards from the start to `board`, by following parents, if
+    // the search reached it.
+    pub fn path_to(&self, board: &[u8]) -> Option<(Vec<Move>, Vec<Vec<u8>>)> {
+        if !self.contains(board) {
+            return None;
+        }
+
+        let mut current = rank(board);
+        let mut boards = vec![board.to_vec()];
+        while current != self.root {
+            current = self.parents[current] as usize;
+            boards.push(unrank(current, self.tiles));
+        }
+        boards.reverse();
+
+        let moves = boards
+            .windows(2)
+            .map(|pair| move_between(&pair[0], &pair[1], self.width))
+            .collect();
+
+        Some((moves, boards))
+    }
+
+    pub fn into_result(self) -> SearchResult {
+        let path = match self.goal {
+            Some(goal) if !self.interrupted => self.path_to(&unrank(goal, self.tiles)),
+            _ => None,
+        };
+        let (actions, states) = path.clone().unwrap_or_default();
+
+        SearchResult {
+            found: path.is_some(),
+            interrupted: self.interrupted,
+            cost: actions.len(),
+            actions,
+            states,
+            stats: self.stats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranking() {
+        assert_eq!(rank(&[0, 1, 2, 3]), 0);
+        assert_eq!(rank(&[3, 2, 1, 0]), 23);
+        assert_eq!(rank(&[1, 0, 2, 3]), 6);
+
+        for index in [0, 1, 1000, 362879] {
+            assert_eq!(rank(&unrank(index, 9)), index);
+        }
+    }
+
+    #[test]
+    fn test_exhaustive_breadth_first() {
+        let game = EightPuzzleState::start(
+            "867/254/301".parse().unwrap(),
+            "123/456/780".parse().unwrap(),
+        )
+        .unwrap();
+
+        let tree = RankedTree::search(&game, true, &mut ()).unwrap();
+        assert_eq!(tree.reached(), 181440);
+        assert_eq!(tree.stats.nodes_expanded, 181440);
+        assert!(!tree.contains(&[2, 1, 3, 4, 5, 6, 7, 8, 0]));
+
+        let (moves, boards) = tree.path_to(&[1, 2, 3, 4, 5, 6, 7, 8, 0]).unwrap();
+        assert_eq!(moves.len(), 31);
+        assert_eq!(boards.first().map(Vec::as_slice), Some(game.board()));
+
+        // Replaying the moves leads through the same boards.
+        let replayed = moves
+            .iter()
+            .fold(vec![game.board().to_vec()], |mut boards, kind| {
+                let mut board = boards.last().unwrap().clone();
+                let blank = board.iter().position(|&tile| tile == 0).unwrap();
+                let next = match kind {
+                    Move::Left => blank - 1,
+                    Move::Right => blank + 1,
+                    Move::Up => blank - 3,
+                    Move::Down => blank + 3,
+                };
+                board.swap(blank, next);
+                boards.push(board);
+                boards
+            });
+        assert_eq!(replayed, boards);
+
+        let result = game.ranked_breadth_first(&mut ()).unwrap();
+        assert!(result.found);
+        assert_eq!(result.cost, 31);
+        assert_eq!(result.states, boards);
+        assert!(result.stats.nodes_expanded < 181440);
+
+        let result = game
+            .ranked_breadth_first(&mut search::ExpansionLimit::new(&mut (), 10))
+            .unwrap();
+        assert!(result.interrupted && !result.found);
+        assert_eq!(result.stats.nodes_expanded, 10);
+    }
+
+    #[test]
+    fn test_search_events() {
+        let game =
+            EightPuzzleState::start("03/21".parse().unwrap(), "12/30".parse().unwrap()).unwrap();
+
+        // Every board reached is reported once, numbered in order, with the
+        // move from the node it was reached from.
+        let mut boards: Vec<Vec<u8>> = Vec::new();
+        let mut expanded = Vec::new();
+        let tree = {
+            let mut observer = |event: SearchEvent<'_, Move, EightPuzzleState>| match event {
+                SearchEvent::NodeGenerated {
+                    id, parent, state, ..
+                } => {
+                    assert_eq!(id, boards.len());
+                    if let Some((parent, kind)) = parent {
+                        assert_eq!(&move_between(&boards[parent], state.board(), 2), kind);
+                    }
+                    boards.push(state.board().to_vec());
+                }
+                SearchEvent::NodeExpanded { id } => {
+                    assert!(id < boards.len());
+                    expanded.push(id);
+                }
+                _ => {}
+            };
+            RankedTree::search(&game, true, &mut observer).unwrap()
+        };
+
+        assert_eq!(boards.len(), tree.reached());
+        assert_eq!(boards.len(), 12);
+        assert_eq!(expanded, (0..12).collect::<Vec<_>>());
+        assert!(boards.iter().all(|board| tree.contains(board)));
+    }
+}
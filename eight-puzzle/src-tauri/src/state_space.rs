@@ -0,0 +1,207 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    eight_puzzle::EightPuzzleState,
+    ranking::{self, RankError},
+};
+
+const UNREACHED: u8 = u8::MAX;
+
+// What an exhaustive search from a target found out about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub reachable: usize,
+    // The most moves any reachable board needs.
+    pub diameter: usize,
+    // Number of boards at every distance from the target, starting with the
+    // target itself.
+    pub histogram: Vec<usize>,
+    // Every board at the largest distance.
+    pub hardest: Vec<Vec<u8>>,
+    pub elapsed_ms: f64,
+}
+
+// Optimal solution lengths of every board that can reach a target, found by
+// a breadth-first search backwards from it. Moves are their own inverse, so
+// this is a plain breadth-first search from the target. Distances are stored
+// by rank, so looking one up takes a ranking and an index.
+pub struct StateSpace {
+    width: usize,
+    tiles: usize,
+    distances: Vec<u8>,
+    histogram: Vec<usize>,
+    elapsed_ms: f64,
+}
+
+impl StateSpace {
+    // Explores every board that can reach the target of `game`, for boards of
+    // up to `ranking::MAX_TILES` tiles.
+    pub fn explore(game: &EightPuzzleState) -> Result<StateSpace, RankError> {
+        let started = Instant::now();
+        let (width, tiles) = (game.width(), game.board().len());
+        ranking::check(tiles)?;
+
+        let permutations: usize = (1..=tiles).product();
+        let mut distances = vec![UNREACHED; permutations];
+        let mut histogram = Vec::new();
+
+        // One layer of ranks per distance, each expanded into the next.
+        let mut layer = vec![ranking::rank(game.target())];
+        distances[layer[0]] = 0;
+
+        while !layer.is_empty() {
+            let distance = histogram.len() as u8 + 1;
+            histogram.push(layer.len());
+
+            let mut next_layer = Vec::new();
+            for current in layer {
+                for next in ranking::neighbours(&ranking::unrank(current, tiles), width) {
+                    if distances[next] == UNREACHED {
+                        distances[next] = distance;
+                        next_layer.push(next);
+                    }
+                }
+            }
+
+            layer = next_layer;
+        }
+
+        Ok(StateSpace {
+            width,
+            tiles,
+            distances,
+            histogram,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        })
+    }
+
+    // Optimal number of moves from `board` to the target, `None` if the
+    // board cannot reach it or has a different size.
+    pub fn distance(&self, board: &[u8]) -> Option<usize> {
+        if board.len() != self.tiles {
+            return None;
+        }
+
+        match self.distances[ranking::rank(board)] {
+            UNREACHED => None,
+            distance => Some(distance as usize),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn tiles(&self) -> usize {
+        self.tiles
+    }
+
+    // Rank and distance of every board that can reach the target.
+    pub fn distances(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.distances
+            .iter()
+            .enumerate()
+            .filter(|(_, &distance)| distance != UNREACHED)
+            .map(|(rank, &distance)| (rank, distance as usize))
+    }
+
+    pub fn summary(&self) -> Summary {
+        let diameter = self.histogram.len() - 1;
+
+        let hardest = self
+            .distances
+            .iter()
+            .enumerate()
+            .filter(|(_, &distance)| distance as usize == diameter)
+            .map(|(rank, _)| ranking::unrank(rank, self.tiles))
+            .collect();
+
+        Summary {
+            reachable: self.histogram.iter().sum(),
+            diameter,
+            histogram: self.histogram.clone(),
+            hardest,
+            elapsed_ms: self.elapsed_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    fn explore(board: &str, target: &str) -> (EightPuzzleState, StateSpace) {
+        let game = EightPuzzleState::start(
+            board.parse::<Board>().unwrap(),
+            target.parse::<Board>().unwrap(),
+        )
+        .unwrap();
+        let space = StateSpace::explore(&game).unwrap();
+        (game, space)
+    }
+
+    #[test]
+    fn test_eight_puzzle_space() {
+        let (game, space) = explore("867/254/301", "123/456/780");
+        let summary = space.summary();
+
+        assert_eq!(summary.reachable, 181440);
+        assert_eq!(summary.diameter, 31);
+        assert_eq!(summary.histogram.len(), 32);
+        assert_eq!(&summary.histogram[..4], &[1, 2, 4, 8]);
+        assert_eq!(
+            summary.hardest,
+            vec![
+                vec![6, 4, 7, 8, 5, 0, 3, 2, 1],
+                vec![8, 6, 7, 2, 5, 4, 3, 0, 1]
+            ]
+        );
+
+        assert_eq!(space.distance(game.board()), Some(31));
+        assert_eq!(space.distance(game.target()), Some(0));
+        assert_eq!(space.distance(&[2, 1, 3, 4, 5, 6, 7, 8, 0]), None);
+        assert_eq!(space.distance(&[1, 2, 3, 0]), None);
+
+        // Every optimal search agrees with the table.
+        let mut checked = 0;
+        for rank in (0..362880).step_by(24001) {
+            let board = ranking::unrank(rank, 9);
+            let distance = match space.distance(&board) {
+                Some(distance) => distance,
+                None => continue,
+            };
+
+            checked += 1;
+            let game = EightPuzzleState::start(
+                Board::new(3, 3, board).unwrap(),
+                Board::new(3, 3, game.target().to_vec()).unwrap(),
+            )
+            .unwrap();
+            for heuristic_fn in [
+                EightPuzzleState::manhattan_distance,
+                EightPuzzleState::linear_conflict,
+            ] {
+                assert_eq!(game.astar_search(heuristic_fn, &mut ()).cost, distance);
+            }
+            assert_eq!(game.bidirectional_breadth_first(&mut ()).cost, distance);
+        }
+        assert!(checked > 5);
+    }
+
+    #[test]
+    fn test_other_targets() {
+        let (_, space) = explore("3 1 / 2 0", "1 2 / 3 0");
+        let summary = space.summary();
+        assert_eq!(summary.reachable, 12);
+        assert_eq!(summary.diameter, 6);
+        assert_eq!(summary.hardest.len(), 1);
+
+        // The blank in the middle makes the space smaller in diameter.
+        let (_, space) = explore("567/408/321", "123/804/765");
+        assert_eq!(space.summary().reachable, 181440);
+        assert_eq!(space.summary().diameter, 30);
+    }
+}
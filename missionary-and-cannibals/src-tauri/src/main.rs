@@ -2,16 +2,141 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod mns;
+mod river;
+
+use search::{graphviz::Graphviz, SearchStats};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+struct TreeResponse<R, T> {
+    #[serde(flatten)]
+    result: R,
+    // The DOT statements added by each step, which the frontend replays
+    // into frames as it animates them.
+    deltas: Vec<String>,
+    // A breadth-first search of the same setup, run without drawing it, for
+    // comparing the number of expanded nodes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breadth_first: Option<SearchStats>,
+    // Every node of a breadth-first or depth-first search, parents first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree: Option<T>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum Algorithm {
+    BreadthFirst,
+    DepthFirst,
+    Greedy,
+    AStar,
+}
 
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
+//
+// All searches stop at the first solution. Setups without a solution still
+// return the whole explored tree, with `found` unset. The heuristic is only
+// used by greedy search and A*.
+#[tauri::command]
+fn generate_tree(
+    missionaries: u8,
+    cannibals: u8,
+    capacity: u8,
+    algorithm: Algorithm,
+    heuristic: Option<mns::Heuristic>,
+) -> Result<TreeResponse<mns::SearchResult, mns::Tree>, mns::CrossingError> {
+    let game = mns::Crossing::new(missionaries, cannibals, capacity)?;
+    let heuristic = heuristic.unwrap_or_default();
+    let mut graphviz = Graphviz::new(&game);
+    let (result, tree) = match algorithm {
+        Algorithm::BreadthFirst => {
+            let (result, tree) = game.build_breadth_first_tree(&mut graphviz);
+            (result, Some(tree))
+        }
+        Algorithm::DepthFirst => {
+            let (result, tree) = game.build_depth_first_tree(&mut graphviz);
+            (result, Some(tree))
+        }
+        Algorithm::Greedy => (game.greedy_search(heuristic, &mut graphviz), None),
+        Algorithm::AStar => (game.astar_search(heuristic, &mut graphviz), None),
+    };
+
+    let breadth_first = match algorithm {
+        Algorithm::BreadthFirst => None,
+        _ => Some(game.build_breadth_first_tree(&mut ()).0.stats),
+    };
+
+    return Ok(TreeResponse {
+        result,
+        deltas: graphviz.into_deltas().concat(),
+        breadth_first,
+        tree,
+    });
+}
+
+// Lists every solution that never repeats a state, drawing the whole tree of
+// such paths.
 #[tauri::command]
-fn generate_tree() -> Vec<String> {
-    return mns::State::build_breadth_first_tree();
+fn list_solutions(
+    missionaries: u8,
+    cannibals: u8,
+    capacity: u8,
+) -> Result<TreeResponse<mns::Solutions, mns::Tree>, mns::CrossingError> {
+    let game = mns::Crossing::new(missionaries, cannibals, capacity)?;
+    let mut graphviz = Graphviz::new(&game);
+    let result = game.all_solutions(&mut graphviz);
+
+    return Ok(TreeResponse {
+        result,
+        deltas: graphviz.into_deltas().concat(),
+        breadth_first: None,
+        tree: None,
+    });
+}
+
+#[derive(Serialize)]
+struct Puzzle {
+    name: String,
+    definition: &'static str,
+}
+
+// The puzzle definitions that ship with the app, for the user to pick from
+// and edit.
+#[tauri::command]
+fn list_puzzles() -> Vec<Puzzle> {
+    return river::PUZZLES
+        .iter()
+        .map(|definition| Puzzle {
+            name: river::Definition::parse(definition).unwrap().name,
+            definition,
+        })
+        .collect();
+}
+
+// Solves a puzzle from its TOML or JSON definition.
+#[tauri::command]
+fn solve_puzzle(
+    definition: String,
+) -> Result<TreeResponse<river::SearchResult, river::Tree>, river::DefinitionError> {
+    let game = river::RiverCrossing::new(river::Definition::parse(&definition)?)?;
+    let mut graphviz = Graphviz::new(&game);
+    let (result, tree) = game.build_breadth_first_tree(&mut graphviz);
+
+    return Ok(TreeResponse {
+        result,
+        deltas: graphviz.into_deltas().concat(),
+        breadth_first: None,
+        tree: Some(tree),
+    });
 }
 
 fn main() {
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![generate_tree])
+        .invoke_handler(tauri::generate_handler![
+            generate_tree,
+            list_solutions,
+            list_puzzles,
+            solve_puzzle
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
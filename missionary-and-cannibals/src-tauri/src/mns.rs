@@ -1,28 +1,39 @@
-use std::collections::VecDeque;
-use std::io::{BufRead, BufReader, Error, Write};
-use std::{fmt::Display, fs::File};
+use std::fmt::Display;
 
+use search::{Problem, SearchObserver};
 use serde::{Deserialize, Serialize};
 
-const N: u8 = 3;
+// A boat load, given as the number of missionaries and cannibals aboard.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    missionaries: u8,
+    cannibals: u8,
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Move {
-    Missionary,
-    Cannibal,
-    TwoMissionaries,
-    TwoCannibals,
-    MissionaryCannibal,
+// Written as in "2M 1C", which keeps the edge labels of the graph short.
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut people = Vec::new();
+        if self.missionaries > 0 {
+            people.push(format!("{}M", self.missionaries));
+        }
+        if self.cannibals > 0 {
+            people.push(format!("{}C", self.cannibals));
+        }
+        write!(f, "{}", people.join(" "))
+    }
 }
 
+pub type SearchResult = search::SearchResult<Move, State>;
+pub type Solutions = search::SolutionSet<Move, State>;
+pub type Tree = search::SearchTree<Move, State>;
+
+// The people left on the starting bank, and whether the boat is there.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct State {
     missionaries: u8,
     cannibals: u8,
     boat: bool,
-
-    sail_history: Vec<Move>,
-    children: Vec<State>,
 }
 
 impl ToString for State {
@@ -45,311 +56,419 @@ impl PartialEq for State {
 }
 
 impl State {
-    fn new() -> State {
-        State {
-            missionaries: N,
-            cannibals: N,
-            boat: true,
-            sail_history: Vec::new(),
-            children: Vec::new(),
-        }
+    fn is_game_complete(&self) -> bool {
+        self.missionaries == 0 && self.cannibals == 0
     }
 
-    pub fn last_move(&self) -> Option<Move> {
-        self.sail_history.last().cloned()
+    fn repr(&self) -> u64 {
+        return self.missionaries as u64 | (self.cannibals as u64) << 8 | (self.boat as u64) << 16;
     }
+}
 
-    pub fn is_terminal(&self) -> bool {
-        self.children == vec![]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossingError {
+    // Nobody could ever cross.
+    NoSeats,
+    // The missionaries are outnumbered before anyone sails.
+    UnsafeStart { missionaries: u8, cannibals: u8 },
+}
+
+impl Display for CrossingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrossingError::NoSeats => write!(f, "the boat needs at least one seat"),
+            CrossingError::UnsafeStart {
+                missionaries,
+                cannibals,
+            } => write!(
+                f,
+                "{} missionaries are outnumbered by {} cannibals from the start",
+                missionaries, cannibals
+            ),
+        }
     }
+}
 
-    pub fn discover_neighbours(&mut self) -> Vec<&mut State> {
-        let mut next_states: Vec<State> = Vec::new();
+impl std::error::Error for CrossingError {}
 
-        let mut next_state = self.clone();
-        if next_state.sail(Move::Missionary) == true {
-            next_states.push(next_state)
-        }
+// Missionaries may never be outnumbered by cannibals, neither on a bank nor
+// in the boat, unless there are no missionaries there at all.
+fn is_safe(missionaries: u8, cannibals: u8) -> bool {
+    missionaries == 0 || missionaries >= cannibals
+}
 
-        let mut next_state = self.clone();
-        if next_state.sail(Move::Cannibal) == true {
-            next_states.push(next_state);
-        }
+// Lower bounds on the crossings left, for the informed searches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Heuristic {
+    // ceil(people on the starting bank / capacity), as if nobody had to row
+    // the boat back.
+    PeopleLeft,
+    // Every round trip takes at most capacity - 1 people across, since one of
+    // them has to bring the boat back, and only the last crossing takes a
+    // full boat. Plain ceil(people / (capacity - 1)) would overestimate once
+    // everyone left fits in the boat.
+    #[default]
+    RoundTrips,
+}
 
-        let mut next_state = self.clone();
-        if next_state.sail(Move::TwoMissionaries) == true {
-            next_states.push(next_state);
-        }
+// A river crossing with any number of missionaries and cannibals and a boat
+// with room for `capacity` people.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crossing {
+    missionaries: u8,
+    cannibals: u8,
+    capacity: u8,
+    loads: Vec<Move>,
+}
 
-        let mut next_state = self.clone();
-        if next_state.sail(Move::TwoCannibals) == true {
-            next_states.push(next_state);
+impl Crossing {
+    pub fn new(missionaries: u8, cannibals: u8, capacity: u8) -> Result<Crossing, CrossingError> {
+        if capacity == 0 {
+            return Err(CrossingError::NoSeats);
         }
 
-        let mut next_state = self.clone();
-        if next_state.sail(Move::MissionaryCannibal) == true {
-            next_states.push(next_state);
+        if !is_safe(missionaries, cannibals) {
+            return Err(CrossingError::UnsafeStart {
+                missionaries,
+                cannibals,
+            });
         }
 
-        self.children = next_states.clone();
-
-        return self.children.iter_mut().collect();
-    }
-
-    fn is_game_complete(&self) -> bool {
-        self.missionaries == 0 && self.cannibals == 0
-    }
-
-    fn repr(&self) -> u64 {
-        return self.missionaries as u64
-            + (self.cannibals as u64 * 10)
-            + ((self.boat as u64) * 100);
-    }
-
-    fn sail(&mut self, kind: Move) -> bool {
-        let mut valid = true;
-
-        let mut next_state = self.clone();
-
-        match kind {
-            Move::Missionary => {
-                if next_state.missionaries > 0 && next_state.boat {
-                    next_state.missionaries -= 1;
-                } else if next_state.missionaries < N && !next_state.boat {
-                    next_state.missionaries += 1;
-                } else {
-                    valid = false;
-                }
-            }
-            Move::Cannibal => {
-                if next_state.cannibals > 0 && next_state.boat {
-                    next_state.cannibals -= 1;
-                } else if next_state.cannibals < N && !next_state.boat {
-                    next_state.cannibals += 1;
-                } else {
-                    valid = false;
-                }
-            }
-            Move::TwoMissionaries => {
-                if next_state.missionaries > 1 && next_state.boat {
-                    next_state.missionaries -= 2;
-                } else if next_state.missionaries < N && !next_state.boat {
-                    next_state.missionaries += 2;
-                } else {
-                    valid = false;
-                }
-            }
-            Move::TwoCannibals => {
-                if next_state.cannibals > 1 && next_state.boat {
-                    next_state.cannibals -= 2;
-                } else if next_state.cannibals < N && !next_state.boat {
-                    next_state.cannibals += 2;
-                } else {
-                    valid = false;
-                }
-            }
-            Move::MissionaryCannibal => {
-                if next_state.missionaries > 0 && next_state.cannibals > 0 && next_state.boat {
-                    next_state.missionaries -= 1;
-                    next_state.cannibals -= 1;
-                } else if next_state.missionaries < N
-                    && next_state.cannibals < N
-                    && !next_state.boat
-                {
-                    next_state.missionaries += 1;
-                    next_state.cannibals += 1;
-                } else {
-                    valid = false;
+        // Every load that fits in the boat, from the fullest down, with at
+        // least one person to row it.
+        let mut loads = Vec::new();
+        for people in (1..=capacity).rev() {
+            for m in (0..=people.min(missionaries)).rev() {
+                let c = people - m;
+                if c <= cannibals && is_safe(m, c) {
+                    loads.push(Move {
+                        missionaries: m,
+                        cannibals: c,
+                    });
                 }
             }
         }
 
-        if next_state.missionaries != 0 && (next_state.missionaries < next_state.cannibals) {
-            valid = false;
+        Ok(Crossing {
+            missionaries,
+            cannibals,
+            capacity,
+            loads,
+        })
+    }
+
+    // Where the load would take the boat, whether or not that is safe.
+    fn sail(&self, state: &State, load: &Move) -> Option<State> {
+        // The load leaves from the bank the boat is on. Counts past `u8::MAX`
+        // are more people than there are, like any count above the totals.
+        let (missionaries, cannibals) = if state.boat {
+            (
+                state.missionaries.checked_sub(load.missionaries)?,
+                state.cannibals.checked_sub(load.cannibals)?,
+            )
+        } else {
+            (
+                state.missionaries.checked_add(load.missionaries)?,
+                state.cannibals.checked_add(load.cannibals)?,
+            )
+        };
+
+        if missionaries > self.missionaries || cannibals > self.cannibals {
+            return None;
         }
 
-        let missionary_next_side = N as i8 - next_state.missionaries as i8;
-        let cannibal_next_side = N as i8 - next_state.cannibals as i8;
+        Some(State {
+            missionaries,
+            cannibals,
+            boat: !state.boat,
+        })
+    }
 
-        if next_state.cannibals > N
-            || next_state.missionaries > N
-            || missionary_next_side < 0
-            || cannibal_next_side < 0
-        {
-            valid = false;
+    // The bank where missionaries are outnumbered, if any.
+    fn violation(&self, state: &State) -> Option<&'static str> {
+        if !is_safe(state.missionaries, state.cannibals) {
+            return Some("missionaries outnumbered on the starting bank");
         }
 
-        if missionary_next_side != 0 && (missionary_next_side < cannibal_next_side) {
-            valid = false;
+        if !is_safe(
+            self.missionaries - state.missionaries,
+            self.cannibals - state.cannibals,
+        ) {
+            return Some("missionaries outnumbered on the far bank");
         }
 
-        if valid {
-            self.boat = !self.boat;
-            self.missionaries = next_state.missionaries;
-            self.cannibals = next_state.cannibals;
-            self.sail_history.push(kind);
-            // self.neighbour = [None, None, None, None, None];
-        } else {
-            // println!("Invalid move");
-        }
-        return valid;
+        None
     }
 
-    pub fn build_breadth_first_tree() -> Vec<String> {
-        let mut game = State::new();
-        // let mut graphviz_nodes = String::new();
-        // let mut graphviz_edges = String::new();
-        let mut graphviz = vec![String::new()];
-
-        let mut queue: VecDeque<&mut State> = VecDeque::new();
-        let mut visited: Vec<u64> = Vec::new();
+    // Crossings needed to take `people` across with the boat on their bank.
+    fn crossings_from_start(&self, people: usize) -> usize {
+        let capacity = self.capacity as usize;
 
-        graphviz.last_mut().unwrap().push_str(&format!(
-            "    {} [label=\"<< {}, {}, {}>>\", fillcolor=blue, style=filled];\n",
-            game.to_string(),
-            game.missionaries,
-            game.cannibals,
-            game.boat
-        ));
-
-        graphviz.push(graphviz.last().unwrap().clone());
+        if people <= capacity {
+            return people.min(1);
+        }
 
-        queue.push_back(&mut game);
+        let round_trips = (people - capacity).div_ceil((capacity - 1).max(1));
+        2 * round_trips + 1
+    }
 
-        let mut current_state;
-        let mut neighbours;
+    pub fn heuristic(&self, heuristic: Heuristic, state: &State) -> usize {
+        let people = state.missionaries as usize + state.cannibals as usize;
 
-        let mut found = false;
+        match heuristic {
+            Heuristic::PeopleLeft => people.div_ceil(self.capacity as usize),
+            Heuristic::RoundTrips if state.boat || people == 0 => self.crossings_from_start(people),
+            // Someone has to bring the boat back first.
+            Heuristic::RoundTrips => 1 + self.crossings_from_start(people + 1),
+        }
+    }
 
-        while queue.len() > 0 {
-            current_state = queue.pop_front().unwrap();
+    // The result comes with every node the search generated. When there is no
+    // solution, that is every state reachable from the start.
+    pub fn build_breadth_first_tree(
+        &self,
+        observer: &mut impl SearchObserver<Move, State>,
+    ) -> (SearchResult, Tree) {
+        search::breadth_first_tree(self, observer)
+    }
 
-            let current_state_clone = current_state.clone();
+    pub fn build_depth_first_tree(
+        &self,
+        observer: &mut impl SearchObserver<Move, State>,
+    ) -> (SearchResult, Tree) {
+        search::depth_first_tree(self, observer)
+    }
 
-            visited.push(current_state.repr());
+    pub fn astar_search(
+        &self,
+        heuristic: Heuristic,
+        observer: &mut impl SearchObserver<Move, State>,
+    ) -> SearchResult {
+        search::astar_search(self, |state| self.heuristic(heuristic, state), observer)
+    }
 
-            neighbours = current_state.discover_neighbours();
+    pub fn greedy_search(
+        &self,
+        heuristic: Heuristic,
+        observer: &mut impl SearchObserver<Move, State>,
+    ) -> SearchResult {
+        search::greedy_best_first_search(self, |state| self.heuristic(heuristic, state), observer)
+    }
 
-            for neighbour in neighbours {
-                let neighbour_clone = neighbour.clone();
+    // Every solution that never returns to a state it has already passed.
+    pub fn all_solutions(&self, observer: &mut impl SearchObserver<Move, State>) -> Solutions {
+        search::all_simple_paths(self, observer)
+    }
+}
 
-                if visited.iter().find(|x| **x == neighbour.repr()).is_some() {
-                    graphviz.push(graphviz.last().unwrap().clone());
-                    continue;
-                } else if queue.contains(&neighbour) {
-                    graphviz.push(graphviz.last().unwrap().clone());
-                    continue;
-                } else {
-                    queue.push_back(neighbour);
-                }
+impl Problem for Crossing {
+    type State = State;
+    type Action = Move;
+    type Key = u64;
 
-                let color = if neighbour_clone.is_game_complete() {
-                    "green"
-                } else {
-                    "white"
-                };
-
-                graphviz.last_mut().unwrap().push_str(&format!(
-                    "    {} [label=\"<< {}, {}, {}>>\", fillcolor={}, style=filled];\n",
-                    neighbour_clone.to_string(),
-                    neighbour_clone.missionaries,
-                    neighbour_clone.cannibals,
-                    neighbour_clone.boat,
-                    color,
-                ));
-
-                graphviz.last_mut().unwrap().push_str(&format!(
-                    "    {} -> {} [label=\"{:?}\", color=red];\n",
-                    current_state_clone.to_string(),
-                    neighbour_clone.to_string(),
-                    neighbour_clone.sail_history.last().unwrap()
-                ));
-
-                graphviz.push(graphviz.last().unwrap().clone());
-
-                if neighbour_clone.is_game_complete() {
-                    found = true;
-                    break;
-                }
-            }
-            if found {
-                break;
-            }
+    fn initial_state(&self) -> State {
+        State {
+            missionaries: self.missionaries,
+            cannibals: self.cannibals,
+            boat: true,
         }
+    }
 
-        return graphviz
+    fn successors(&self, state: &State) -> Vec<(Move, State, usize)> {
+        self.loads
             .iter()
-            .map(|x| format!("digraph {{\n{}}}", x))
-            .collect();
+            .filter_map(|load| Some((*load, self.sail(state, load)?, 1)))
+            .filter(|(_, next, _)| self.violation(next).is_none())
+            .collect()
     }
 
-    pub fn build_depth_first_tree() -> Vec<String> {
-        let mut game = State::new();
-        // let mut graphviz_nodes = String::new();
-        // let mut graphviz_edges = String::new();
-        let mut graphviz = vec![String::new()];
-
-        let mut stack: Vec<&mut State> = Vec::new();
-        let mut visited: Vec<State> = Vec::new();
+    fn rejected_successors(&self, state: &State) -> Vec<(Move, State, String)> {
+        self.loads
+            .iter()
+            .filter_map(|load| {
+                let next = self.sail(state, load)?;
+                let reason = self.violation(&next)?;
+                Some((*load, next, reason.to_string()))
+            })
+            .collect()
+    }
 
-        graphviz.last_mut().unwrap().push_str(&format!(
-            "    {} [label=\"<< {}, {}, {}>>\", fillcolor=blue];\n",
-            game.to_string(),
-            game.missionaries,
-            game.cannibals,
-            game.boat
-        ));
+    fn is_goal(&self, state: &State) -> bool {
+        state.is_game_complete()
+    }
 
-        graphviz.push(graphviz.last().unwrap().clone());
+    fn key(&self, state: &State) -> u64 {
+        state.repr()
+    }
 
-        stack.push(&mut game);
+    fn label(&self, state: &State) -> String {
+        format!(
+            "<< {}, {}, {}>>",
+            state.missionaries, state.cannibals, state.boat
+        )
+    }
+}
 
-        let mut current_state;
-        let mut neighbours;
-        while stack.len() > 0 {
-            current_state = stack.pop().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::SearchEvent;
 
-            let current_state_clone = current_state.clone();
+    #[test]
+    fn test_boat_loads() {
+        let loads: Vec<String> = Crossing::new(3, 3, 2)
+            .unwrap()
+            .loads
+            .iter()
+            .map(|load| format!("{:?}", load))
+            .collect();
+        assert_eq!(loads, ["2M", "1M 1C", "2C", "1M", "1C"]);
+
+        // A missionary may not share the boat with two cannibals.
+        let loads = Crossing::new(5, 5, 3).unwrap().loads;
+        assert_eq!(loads.len(), 8);
+        assert!(!loads.contains(&Move {
+            missionaries: 1,
+            cannibals: 2
+        }));
+
+        assert_eq!(Crossing::new(3, 3, 0), Err(CrossingError::NoSeats));
+        assert_eq!(
+            Crossing::new(2, 3, 2),
+            Err(CrossingError::UnsafeStart {
+                missionaries: 2,
+                cannibals: 3
+            })
+        );
+    }
 
-            visited.push(current_state.clone());
+    #[test]
+    fn test_depth_first_search() {
+        let crossing = Crossing::new(3, 3, 2).unwrap();
 
-            neighbours = current_state.discover_neighbours();
+        let mut goals = 0;
+        let (result, tree) =
+            crossing.build_depth_first_tree(&mut |event: SearchEvent<'_, Move, State>| {
+                if let SearchEvent::GoalFound { .. } = event {
+                    goals += 1;
+                }
+            });
+        assert!(result.found);
+        assert!(result.states.last().unwrap().is_game_complete());
+        assert_eq!(goals, 1);
+
+        // The goal is the last node generated, and its ancestors are the
+        // solution.
+        let goal = tree.len() - 1;
+        assert_eq!(tree.ancestors(goal).count(), result.states.len());
+        assert_eq!(tree.path_to(goal), (result.actions, result.states));
+        assert_eq!(tree.get(goal).map(|node| node.depth), Some(11));
+
+        // The classic puzzle has only the four well-known solutions, while a
+        // third seat allows many detours.
+        let solutions = crossing.all_solutions(&mut ());
+        assert_eq!(solutions.solutions.len(), 4);
+        assert_eq!(solutions.optimal, 4);
+        assert!(solutions
+            .solutions
+            .iter()
+            .all(|solution| solution.cost == 11));
 
-            for neighbour in neighbours {
-                let neighbour_clone = neighbour.clone();
+        let solutions = Crossing::new(3, 3, 3).unwrap().all_solutions(&mut ());
+        assert_eq!(solutions.solutions.len(), 94);
+        assert_eq!(solutions.optimal, 6);
+        assert_eq!(solutions.stats.solution_depth, Some(5));
+    }
 
-                if visited.iter().find(|x| *x == neighbour).is_some() {
-                    graphviz.push(graphviz.last().unwrap().clone());
-                    continue;
-                } else if stack.contains(&neighbour) {
-                    // graphviz.push(graphviz.last().unwrap().clone());
-                    // continue;
-                } else {
-                    stack.push(neighbour);
+    #[test]
+    fn test_informed_searches() {
+        for (missionaries, cannibals, capacity) in
+            [(3, 3, 2), (5, 5, 3), (6, 6, 4), (4, 2, 2), (250, 5, 10)]
+        {
+            let crossing = Crossing::new(missionaries, cannibals, capacity).unwrap();
+            let (breadth_first, _) = crossing.build_breadth_first_tree(&mut ());
+
+            for heuristic in [Heuristic::PeopleLeft, Heuristic::RoundTrips] {
+                let result = crossing.astar_search(heuristic, &mut ());
+                assert_eq!(result.cost, breadth_first.cost);
+                assert!(result.stats.nodes_expanded <= breadth_first.stats.nodes_expanded);
+
+                // No state on an optimal path is estimated above the
+                // crossings it still needs.
+                for (depth, state) in result.states.iter().enumerate() {
+                    assert!(crossing.heuristic(heuristic, state) <= result.cost - depth);
                 }
 
-                graphviz.last_mut().unwrap().push_str(&format!(
-                    "    {} [label=\"<< {}, {}, {}>>\", fillcolor=blue];\n",
-                    neighbour_clone.to_string(),
-                    neighbour_clone.missionaries,
-                    neighbour_clone.cannibals,
-                    neighbour_clone.boat
-                ));
-
-                graphviz.last_mut().unwrap().push_str(&format!(
-                    "    {} -> {} [label=\"{:?}\", color=red];\n",
-                    current_state_clone.to_string(),
-                    neighbour_clone.to_string(),
-                    neighbour_clone.sail_history.last().unwrap()
-                ));
-
-                graphviz.push(graphviz.last().unwrap().clone());
+                let result = crossing.greedy_search(heuristic, &mut ());
+                assert!(result.found);
+                assert!(result.cost >= breadth_first.cost);
             }
         }
 
-        return graphviz
-            .iter()
-            .map(|x| format!("digraph {{\n{}}}", x))
+        let crossing = Crossing::new(3, 3, 2).unwrap();
+        let start = crossing.initial_state();
+        assert_eq!(crossing.heuristic(Heuristic::PeopleLeft, &start), 3);
+        assert_eq!(crossing.heuristic(Heuristic::RoundTrips, &start), 9);
+    }
+
+    #[test]
+    fn test_rejected_moves() {
+        let crossing = Crossing::new(3, 3, 2).unwrap();
+        let start = crossing.initial_state();
+
+        let rejected: Vec<(String, String)> = crossing
+            .rejected_successors(&start)
+            .into_iter()
+            .map(|(load, _, reason)| (format!("{:?}", load), reason))
             .collect();
+        assert_eq!(
+            rejected,
+            [
+                (
+                    "2M".to_string(),
+                    "missionaries outnumbered on the starting bank".to_string()
+                ),
+                (
+                    "1M".to_string(),
+                    "missionaries outnumbered on the starting bank".to_string()
+                )
+            ]
+        );
+        assert_eq!(
+            crossing.successors(&start).len() + rejected.len(),
+            crossing.loads.len()
+        );
+    }
+
+    #[test]
+    fn test_solvable_setups() {
+        for (missionaries, cannibals, capacity, crossings) in [
+            (3, 3, 2, Some(11)),
+            (4, 4, 2, None),
+            (5, 5, 3, Some(11)),
+            (6, 6, 3, None),
+            (6, 6, 4, Some(9)),
+            (4, 2, 2, Some(9)),
+            (0, 3, 1, None),
+            // Loads brought back to the starting bank would overflow a `u8`.
+            (250, 5, 10, Some(57)),
+        ] {
+            let crossing = Crossing::new(missionaries, cannibals, capacity).unwrap();
+            let (result, tree) = crossing.build_breadth_first_tree(&mut ());
+            assert_eq!(
+                tree.len(),
+                result.stats.nodes_generated + 1 - result.stats.duplicates_pruned
+            );
+            let found = if result.found {
+                Some(result.cost)
+            } else {
+                None
+            };
+            assert_eq!(
+                found, crossings,
+                "{} missionaries, {} cannibals, {} seats",
+                missionaries, cannibals, capacity
+            );
+        }
     }
 }
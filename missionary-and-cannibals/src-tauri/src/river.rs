@@ -0,0 +1,616 @@
+use std::fmt::Display;
+
+use search::{Problem, SearchObserver};
+use serde::{Deserialize, Serialize};
+
+// The puzzles that ship with the app.
+pub const PUZZLES: [&str; 3] = [
+    include_str!("../puzzles/missionaries-and-cannibals.toml"),
+    include_str!("../puzzles/wolf-goat-cabbage.toml"),
+    include_str!("../puzzles/jealous-husbands.toml"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    #[serde(default = "one")]
+    pub count: u8,
+    // Whether this entity can take the boat across on its own.
+    #[serde(default = "yes")]
+    pub rows: bool,
+}
+
+fn one() -> u8 {
+    1
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Place {
+    // Either bank, checked after every crossing.
+    #[default]
+    Bank,
+    Boat,
+    Anywhere,
+}
+
+// A grouping that must never occur. The group is forbidden when every
+// condition given holds at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub place: Place,
+    // Shown when the rule rejects a move.
+    #[serde(default)]
+    pub description: Option<String>,
+    // All of these are present.
+    #[serde(default)]
+    pub together: Vec<String>,
+    // At least one of these is present.
+    #[serde(default)]
+    pub any_of: Vec<String>,
+    // None of these is present.
+    #[serde(default)]
+    pub without: Vec<String>,
+    // Some of `group` are present and fewer than of `by`.
+    #[serde(default)]
+    pub outnumbered: Option<Outnumbered>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outnumbered {
+    pub group: String,
+    pub by: String,
+}
+
+// A river crossing puzzle as written in a definition file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definition {
+    pub name: String,
+    // Seats in the boat, rower included.
+    pub capacity: u8,
+    pub entities: Vec<Entity>,
+    #[serde(default)]
+    pub forbidden: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefinitionError {
+    Json(String),
+    Toml(String),
+    NoEntities,
+    NoSeats,
+    NoRower,
+    DuplicateEntity(String),
+    UnknownEntity { rule: usize, name: String },
+    EmptyRule(usize),
+    // The starting bank already breaks a rule.
+    UnsafeStart(String),
+}
+
+impl Display for DefinitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefinitionError::Json(error) => write!(f, "invalid JSON definition: {}", error),
+            DefinitionError::Toml(error) => write!(f, "invalid TOML definition: {}", error),
+            DefinitionError::NoEntities => write!(f, "the definition lists no entities"),
+            DefinitionError::NoSeats => write!(f, "the boat needs at least one seat"),
+            DefinitionError::NoRower => write!(f, "no entity can row the boat"),
+            DefinitionError::DuplicateEntity(name) => {
+                write!(f, "the entity {:?} is listed more than once", name)
+            }
+            DefinitionError::UnknownEntity { rule, name } => {
+                write!(
+                    f,
+                    "rule {} refers to an unknown entity {:?}",
+                    rule + 1,
+                    name
+                )
+            }
+            DefinitionError::EmptyRule(rule) => {
+                write!(
+                    f,
+                    "rule {} has no conditions and would forbid everything",
+                    rule + 1
+                )
+            }
+            DefinitionError::UnsafeStart(rule) => {
+                write!(f, "the starting bank already breaks the rule {:?}", rule)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+impl Definition {
+    // Definitions starting with '{' are read as JSON, anything else as TOML.
+    pub fn parse(text: &str) -> Result<Definition, DefinitionError> {
+        if text.trim_start().starts_with('{') {
+            serde_json::from_str(text).map_err(|error| DefinitionError::Json(error.to_string()))
+        } else {
+            toml::from_str(text).map_err(|error| DefinitionError::Toml(error.to_string()))
+        }
+    }
+}
+
+// A rule with the entity names resolved to indices.
+#[derive(Debug, Clone)]
+struct Constraint {
+    place: Place,
+    description: String,
+    together: Vec<usize>,
+    any_of: Vec<usize>,
+    without: Vec<usize>,
+    outnumbered: Option<(usize, usize)>,
+}
+
+impl Constraint {
+    fn forbids(&self, counts: &[u8]) -> bool {
+        self.together.iter().all(|&entity| counts[entity] > 0)
+            && (self.any_of.is_empty() || self.any_of.iter().any(|&entity| counts[entity] > 0))
+            && self.without.iter().all(|&entity| counts[entity] == 0)
+            && self
+                .outnumbered
+                .is_none_or(|(group, by)| counts[group] > 0 && counts[group] < counts[by])
+    }
+
+    fn applies_to_banks(&self) -> bool {
+        self.place != Place::Boat
+    }
+
+    fn applies_to_boat(&self) -> bool {
+        self.place != Place::Bank
+    }
+}
+
+// The entities crossing in one trip, counted per entity type.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Load {
+    counts: Vec<u8>,
+    label: String,
+}
+
+impl std::fmt::Debug for Load {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+// How many of each entity type are on the starting bank, and whether the boat
+// is there.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Bank {
+    counts: Vec<u8>,
+    boat: bool,
+}
+
+pub type SearchResult = search::SearchResult<Load, Bank>;
+pub type Tree = search::SearchTree<Load, Bank>;
+
+// A puzzle ready to be searched, built from a checked definition.
+#[derive(Debug, Clone)]
+pub struct RiverCrossing {
+    entities: Vec<Entity>,
+    constraints: Vec<Constraint>,
+    loads: Vec<Load>,
+}
+
+impl RiverCrossing {
+    pub fn new(definition: Definition) -> Result<RiverCrossing, DefinitionError> {
+        let Definition {
+            capacity,
+            entities,
+            forbidden,
+            ..
+        } = definition;
+
+        if entities.is_empty() {
+            return Err(DefinitionError::NoEntities);
+        }
+
+        if capacity == 0 {
+            return Err(DefinitionError::NoSeats);
+        }
+
+        if !entities
+            .iter()
+            .any(|entity| entity.rows && entity.count > 0)
+        {
+            return Err(DefinitionError::NoRower);
+        }
+
+        for (index, entity) in entities.iter().enumerate() {
+            if entities[..index]
+                .iter()
+                .any(|other| other.name == entity.name)
+            {
+                return Err(DefinitionError::DuplicateEntity(entity.name.clone()));
+            }
+        }
+
+        let mut constraints = Vec::new();
+        for (rule, forbidden) in forbidden.into_iter().enumerate() {
+            let resolve = |name: &String| {
+                entities
+                    .iter()
+                    .position(|entity| entity.name == *name)
+                    .ok_or_else(|| DefinitionError::UnknownEntity {
+                        rule,
+                        name: name.clone(),
+                    })
+            };
+            let resolve_all =
+                |names: &[String]| names.iter().map(resolve).collect::<Result<Vec<_>, _>>();
+
+            let outnumbered = match &forbidden.outnumbered {
+                Some(outnumbered) => {
+                    Some((resolve(&outnumbered.group)?, resolve(&outnumbered.by)?))
+                }
+                None => None,
+            };
+
+            let constraint = Constraint {
+                place: forbidden.place,
+                description: forbidden
+                    .description
+                    .unwrap_or_else(|| format!("rule {}", rule + 1)),
+                together: resolve_all(&forbidden.together)?,
+                any_of: resolve_all(&forbidden.any_of)?,
+                without: resolve_all(&forbidden.without)?,
+                outnumbered,
+            };
+
+            if constraint.together.is_empty()
+                && constraint.any_of.is_empty()
+                && constraint.outnumbered.is_none()
+            {
+                return Err(DefinitionError::EmptyRule(rule));
+            }
+
+            constraints.push(constraint);
+        }
+
+        let counts: Vec<u8> = entities.iter().map(|entity| entity.count).collect();
+        if let Some(constraint) = constraints
+            .iter()
+            .find(|constraint| constraint.applies_to_banks() && constraint.forbids(&counts))
+        {
+            return Err(DefinitionError::UnsafeStart(constraint.description.clone()));
+        }
+
+        let mut crossing = RiverCrossing {
+            entities,
+            constraints,
+            loads: Vec::new(),
+        };
+        crossing.loads = crossing.generate_loads(capacity);
+
+        Ok(crossing)
+    }
+
+    // Every load that fits in the boat, has someone to row it and breaks no
+    // rule for the boat.
+    fn generate_loads(&self, capacity: u8) -> Vec<Load> {
+        let mut loads = Vec::new();
+        let mut counts = vec![0; self.entities.len()];
+
+        self.add_loads(0, capacity, &mut counts, &mut loads);
+        loads
+    }
+
+    fn add_loads(&self, entity: usize, seats: u8, counts: &mut Vec<u8>, loads: &mut Vec<Load>) {
+        if entity == self.entities.len() {
+            let rowed = counts
+                .iter()
+                .zip(&self.entities)
+                .any(|(&count, entity)| count > 0 && entity.rows);
+            let safe = !self
+                .constraints
+                .iter()
+                .any(|constraint| constraint.applies_to_boat() && constraint.forbids(counts));
+
+            if rowed && safe {
+                loads.push(Load {
+                    label: self.describe(counts),
+                    counts: counts.clone(),
+                });
+            }
+            return;
+        }
+
+        for count in 0..=seats.min(self.entities[entity].count) {
+            counts[entity] = count;
+            self.add_loads(entity + 1, seats - count, counts, loads);
+        }
+        counts[entity] = 0;
+    }
+
+    fn describe(&self, counts: &[u8]) -> String {
+        let names: Vec<String> = counts
+            .iter()
+            .zip(&self.entities)
+            .filter(|(&count, _)| count > 0)
+            .map(|(&count, entity)| {
+                if entity.count == 1 {
+                    entity.name.clone()
+                } else {
+                    format!("{} {}", count, entity.name)
+                }
+            })
+            .collect();
+
+        names.join(", ")
+    }
+
+    // The first rule broken on either bank, if any.
+    fn violation(&self, counts: &[u8]) -> Option<&Constraint> {
+        let far: Vec<u8> = counts
+            .iter()
+            .zip(&self.entities)
+            .map(|(&count, entity)| entity.count - count)
+            .collect();
+
+        self.constraints.iter().find(|constraint| {
+            constraint.applies_to_banks()
+                && (constraint.forbids(counts) || constraint.forbids(&far))
+        })
+    }
+
+    // Where the load would take the boat, whether or not that breaks a rule.
+    fn sail(&self, bank: &Bank, load: &Load) -> Option<Bank> {
+        let mut counts = bank.counts.clone();
+
+        for ((count, &moved), entity) in counts.iter_mut().zip(&load.counts).zip(&self.entities) {
+            *count = if bank.boat {
+                count.checked_sub(moved)?
+            } else {
+                match count.checked_add(moved) {
+                    Some(count) if count <= entity.count => count,
+                    _ => return None,
+                }
+            };
+        }
+
+        Some(Bank {
+            counts,
+            boat: !bank.boat,
+        })
+    }
+
+    // The result comes with every node the search generated, as in mns.rs.
+    pub fn build_breadth_first_tree(
+        &self,
+        observer: &mut impl SearchObserver<Load, Bank>,
+    ) -> (SearchResult, Tree) {
+        search::breadth_first_tree(self, observer)
+    }
+
+    pub fn build_depth_first_tree(
+        &self,
+        observer: &mut impl SearchObserver<Load, Bank>,
+    ) -> (SearchResult, Tree) {
+        search::depth_first_tree(self, observer)
+    }
+}
+
+impl Problem for RiverCrossing {
+    type State = Bank;
+    type Action = Load;
+    type Key = Bank;
+
+    fn initial_state(&self) -> Bank {
+        Bank {
+            counts: self.entities.iter().map(|entity| entity.count).collect(),
+            boat: true,
+        }
+    }
+
+    fn successors(&self, bank: &Bank) -> Vec<(Load, Bank, usize)> {
+        self.loads
+            .iter()
+            .filter_map(|load| Some((load.clone(), self.sail(bank, load)?, 1)))
+            .filter(|(_, next, _)| self.violation(&next.counts).is_none())
+            .collect()
+    }
+
+    fn rejected_successors(&self, bank: &Bank) -> Vec<(Load, Bank, String)> {
+        self.loads
+            .iter()
+            .filter_map(|load| {
+                let next = self.sail(bank, load)?;
+                let constraint = self.violation(&next.counts)?;
+                Some((load.clone(), next, constraint.description.clone()))
+            })
+            .collect()
+    }
+
+    fn is_goal(&self, bank: &Bank) -> bool {
+        bank.counts.iter().all(|&count| count == 0)
+    }
+
+    fn key(&self, bank: &Bank) -> Bank {
+        bank.clone()
+    }
+
+    fn label(&self, bank: &Bank) -> String {
+        let far: Vec<u8> = bank
+            .counts
+            .iter()
+            .zip(&self.entities)
+            .map(|(&count, entity)| entity.count - count)
+            .collect();
+
+        let (near_boat, far_boat) = if bank.boat {
+            ("⛵ ", "")
+        } else {
+            ("", " ⛵")
+        };
+        format!(
+            "{}{}\\n~~~\\n{}{}",
+            near_boat,
+            self.describe(&bank.counts),
+            self.describe(&far),
+            far_boat
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(definition: &str) -> Option<usize> {
+        let crossing = RiverCrossing::new(Definition::parse(definition).unwrap()).unwrap();
+        let (result, tree) = crossing.build_breadth_first_tree(&mut ());
+        if result.found {
+            // Goals are detected when they are generated, so the goal is the
+            // last node.
+            assert_eq!(tree.path_to(tree.len() - 1).1, result.states);
+        }
+
+        // Depth-first search finds a solution whenever there is one.
+        let (depth_first, _) = crossing.build_depth_first_tree(&mut ());
+        assert_eq!(depth_first.found, result.found);
+
+        if result.found {
+            Some(result.cost)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_shipped_puzzles() {
+        let crossings: Vec<Option<usize>> =
+            PUZZLES.iter().map(|definition| solve(definition)).collect();
+        assert_eq!(crossings, [Some(11), Some(7), Some(11)]);
+
+        // The same loads as the M&C puzzle in mns.rs.
+        let crossing = RiverCrossing::new(Definition::parse(PUZZLES[0]).unwrap()).unwrap();
+        let loads: Vec<String> = crossing
+            .loads
+            .iter()
+            .map(|load| format!("{:?}", load))
+            .collect();
+        assert_eq!(
+            loads,
+            [
+                "1 cannibal",
+                "2 cannibal",
+                "1 missionary",
+                "1 missionary, 1 cannibal",
+                "2 missionary"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejected_crossings() {
+        let crossing = RiverCrossing::new(Definition::parse(PUZZLES[1]).unwrap()).unwrap();
+        let start = crossing.initial_state();
+
+        let rejected: Vec<(String, String)> = crossing
+            .rejected_successors(&start)
+            .into_iter()
+            .map(|(load, _, reason)| (format!("{:?}", load), reason))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                ("farmer".to_string(), "the wolf eats the goat".to_string()),
+                (
+                    "farmer, cabbage".to_string(),
+                    "the wolf eats the goat".to_string()
+                ),
+                (
+                    "farmer, wolf".to_string(),
+                    "the goat eats the cabbage".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_definitions() {
+        let definition = r#"{
+            "name": "Four missionaries and cannibals",
+            "capacity": 2,
+            "entities": [
+                { "name": "missionary", "count": 4 },
+                { "name": "cannibal", "count": 4 }
+            ],
+            "forbidden": [
+                {
+                    "place": "anywhere",
+                    "outnumbered": { "group": "missionary", "by": "cannibal" }
+                }
+            ]
+        }"#;
+        assert_eq!(solve(definition), None);
+        assert_eq!(
+            solve(&definition.replace("\"capacity\": 2", "\"capacity\": 3")),
+            Some(9)
+        );
+    }
+
+    #[test]
+    fn test_large_groups() {
+        // Loads brought back to the starting bank would overflow a `u8`.
+        let definition = PUZZLES[0]
+            .replace("capacity = 2", "capacity = 10")
+            .replacen("count = 3", "count = 250", 1)
+            .replacen("count = 3", "count = 5", 1);
+        assert_eq!(solve(&definition), Some(57));
+    }
+
+    #[test]
+    fn test_invalid_definitions() {
+        let invalid = |text: &str| Definition::parse(text).and_then(RiverCrossing::new).err();
+
+        assert!(matches!(
+            invalid("name = 1"),
+            Some(DefinitionError::Toml(_))
+        ));
+        assert!(matches!(
+            invalid("{ \"name\": "),
+            Some(DefinitionError::Json(_))
+        ));
+
+        let farmer = "name = \"x\"\ncapacity = 1\n[[entities]]\nname = \"farmer\"\n";
+        assert_eq!(invalid(farmer), None);
+        assert_eq!(
+            invalid(&farmer.replace("capacity = 1", "capacity = 0")),
+            Some(DefinitionError::NoSeats)
+        );
+        assert_eq!(
+            invalid(&format!("{}rows = false\n", farmer)),
+            Some(DefinitionError::NoRower)
+        );
+        assert_eq!(
+            invalid(&format!("{}[[forbidden]]\ntogether = [\"goat\"]\n", farmer)),
+            Some(DefinitionError::UnknownEntity {
+                rule: 0,
+                name: "goat".to_string()
+            })
+        );
+        assert_eq!(
+            invalid(&format!(
+                "{}[[forbidden]]\nwithout = [\"farmer\"]\n",
+                farmer
+            )),
+            Some(DefinitionError::EmptyRule(0))
+        );
+        assert_eq!(
+            invalid(&format!(
+                "{}[[forbidden]]\ndescription = \"alone\"\ntogether = [\"farmer\"]\n",
+                farmer
+            )),
+            Some(DefinitionError::UnsafeStart("alone".to_string()))
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::{collections::HashMap, hash::Hash};
+
+use serde::Serialize;
+
+use crate::Problem;
+
+// Something that happened during a search. Nodes are numbered in the order
+// they are first generated, and states are borrowed from the search, so an
+// observer only pays for what it keeps.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum SearchEvent<'a, A, S> {
+    // Iterative searches start over from the initial state with a new depth
+    // limit or f threshold. Node ids are kept across iterations.
+    IterationStarted {
+        bound: usize,
+    },
+    NodeGenerated {
+        id: usize,
+        // The node this one was generated from and the action taken there,
+        // `None` for the initial state (and the goal state when searching
+        // backwards).
+        parent: Option<(usize, &'a A)>,
+        state: &'a S,
+        depth: usize,
+        g: usize,
+        // Only informed searches estimate the remaining cost.
+        h: Option<usize>,
+        // Generated by the backward half of a bidirectional search, where the
+        // action leads from this node to its parent.
+        backward: bool,
+    },
+    NodeExpanded {
+        id: usize,
+    },
+    // A successor that was dropped because `id` already covers its state.
+    DuplicatePruned {
+        id: usize,
+        parent: usize,
+    },
+    // A successor that breaks a rule of the problem, reported right after
+    // its parent is expanded. It gets an id of its own but is never searched.
+    SuccessorRejected {
+        id: usize,
+        parent: (usize, &'a A),
+        state: &'a S,
+        reason: &'a str,
+    },
+    // Generated beyond the depth limit or f threshold of the current
+    // iteration, so it will not be expanded in this one.
+    NodeCutOff {
+        id: usize,
+    },
+    // For bidirectional search this is the node that reached a state of the
+    // other side, which has a node of its own there.
+    GoalFound {
+        id: usize,
+    },
+    // Sizes after an expansion. For the depth-first searches the frontier is
+    // the current path.
+    FrontierSnapshot {
+        frontier: usize,
+        stored: usize,
+    },
+}
+
+pub trait SearchObserver<A, S> {
+    fn event(&mut self, event: SearchEvent<'_, A, S>);
+
+    // Polled before every expansion. Once it returns true the search stops
+    // and reports what it has found so far as an interrupted result.
+    fn interrupted(&self) -> bool {
+        false
+    }
+}
+
+impl<A, S, F: FnMut(SearchEvent<'_, A, S>)> SearchObserver<A, S> for F {
+    fn event(&mut self, event: SearchEvent<'_, A, S>) {
+        self(event)
+    }
+}
+
+// Ignores every event.
+impl<A, S> SearchObserver<A, S> for () {
+    fn event(&mut self, _event: SearchEvent<'_, A, S>) {}
+}
+
+// Passes events on to another observer and interrupts the search once a
+// number of nodes have been expanded.
+pub struct ExpansionLimit<'o, O: ?Sized> {
+    observer: &'o mut O,
+    remaining: usize,
+}
+
+impl<'o, O: ?Sized> ExpansionLimit<'o, O> {
+    pub fn new(observer: &'o mut O, max_expanded: usize) -> ExpansionLimit<'o, O> {
+        ExpansionLimit {
+            observer,
+            remaining: max_expanded,
+        }
+    }
+}
+
+impl<A, S, O: SearchObserver<A, S> + ?Sized> SearchObserver<A, S> for ExpansionLimit<'_, O> {
+    fn event(&mut self, event: SearchEvent<'_, A, S>) {
+        if let SearchEvent::NodeExpanded { .. } = event {
+            self.remaining = self.remaining.saturating_sub(1);
+        }
+
+        self.observer.event(event);
+    }
+
+    fn interrupted(&self) -> bool {
+        self.remaining == 0 || self.observer.interrupted()
+    }
+}
+
+// Hands out node ids to the searches and forwards their events.
+pub(crate) struct Tracer<'o, K, A, S> {
+    ids: HashMap<K, usize>,
+    next: usize,
+    observer: &'o mut dyn SearchObserver<A, S>,
+}
+
+impl<'o, K: Clone + Eq + Hash, A, S> Tracer<'o, K, A, S> {
+    pub(crate) fn new(observer: &'o mut dyn SearchObserver<A, S>) -> Tracer<'o, K, A, S> {
+        Tracer {
+            ids: HashMap::new(),
+            next: 0,
+            observer,
+        }
+    }
+
+    pub(crate) fn id(&mut self, key: &K) -> usize {
+        let next = &mut self.next;
+        *self.ids.entry(key.clone()).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        })
+    }
+
+    // Reports the expansion of `state`, followed by the successors the
+    // problem rejected there.
+    pub(crate) fn expanded<P: Problem<Action = A, State = S>>(
+        &mut self,
+        problem: &P,
+        id: usize,
+        state: &S,
+    ) {
+        self.emit(SearchEvent::NodeExpanded { id });
+
+        for (action, rejected, reason) in problem.rejected_successors(state) {
+            self.next += 1;
+            self.emit(SearchEvent::SuccessorRejected {
+                id: self.next - 1,
+                parent: (id, &action),
+                state: &rejected,
+                reason: &reason,
+            });
+        }
+    }
+
+    pub(crate) fn emit(&mut self, event: SearchEvent<'_, A, S>) {
+        self.observer.event(event);
+    }
+
+    pub(crate) fn interrupted(&self) -> bool {
+        self.observer.interrupted()
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Problem, SearchEvent, SearchObserver};
+
+// Draws the search tree from the event stream. Instead of a snapshot of the
+// whole graph per step it keeps only the DOT statements each step adds, so
+// memory grows with the size of the tree rather than its square. Iterative
+// searches get a separate list of deltas per iteration.
+pub struct Graphviz<'p, P: Problem> {
+    problem: &'p P,
+    iterations: Vec<Vec<String>>,
+}
+
+impl<'p, P: Problem> Graphviz<'p, P> {
+    pub fn new(problem: &'p P) -> Graphviz<'p, P> {
+        Graphviz {
+            problem,
+            iterations: Vec::new(),
+        }
+    }
+
+    fn deltas(&mut self) -> &mut Vec<String> {
+        if self.iterations.is_empty() {
+            self.iterations.push(Vec::new());
+        }
+
+        self.iterations.last_mut().unwrap()
+    }
+
+    // Starts the next frame with `statements`.
+    fn step(&mut self, statements: String) {
+        self.deltas().push(statements);
+    }
+
+    // Adds to the frame drawn last.
+    fn amend(&mut self, statements: String) {
+        match self.deltas().last_mut() {
+            Some(delta) => delta.push_str(&statements),
+            None => self.step(statements),
+        }
+    }
+
+    pub fn into_deltas(self) -> Vec<Vec<String>> {
+        self.iterations
+    }
+}
+
+impl<P: Problem> SearchObserver<P::Action, P::State> for Graphviz<'_, P> {
+    fn event(&mut self, event: SearchEvent<'_, P::Action, P::State>) {
+        match draw(self.problem, &event) {
+            Some(Drawing::Restart) => self.iterations.push(Vec::new()),
+            Some(Drawing::Step(statements)) => self.step(statements),
+            Some(Drawing::Amend(statements)) => self.amend(statements),
+            None => {}
+        }
+    }
+}
+
+// What drawing a single event adds to the graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Drawing {
+    // Iterative searches draw every iteration from scratch.
+    Restart,
+    // Starts the next frame with these statements.
+    Step(String),
+    // Adds to the frame drawn last.
+    Amend(String),
+}
+
+pub fn draw<P: Problem>(
+    problem: &P,
+    event: &SearchEvent<'_, P::Action, P::State>,
+) -> Option<Drawing> {
+    match *event {
+        SearchEvent::IterationStarted { .. } => Some(Drawing::Restart),
+        SearchEvent::NodeGenerated {
+            id,
+            parent,
+            state,
+            g,
+            h,
+            backward,
+            ..
+        } => {
+            let mut label = problem.label(state);
+            if let Some(h) = h {
+                label = format!("{}\\n\\ng={}, h={}, f={}", label, g, h, g + h);
+            }
+
+            let color = match (&parent, backward) {
+                (None, false) => "blue",
+                (None, true) => "orange",
+                (Some(_), false) => "white",
+                (Some(_), true) => "lightblue",
+            };
+
+            let mut statements = format!(
+                "    n{} [label=\"{}\", fillcolor={}, style=filled];\n",
+                id, label, color,
+            );
+
+            // Backward edges are drawn from child to parent, so every arrow
+            // follows the direction of the action.
+            if let Some((parent, action)) = parent {
+                let (from, to, color) = if backward {
+                    (id, parent, "blue")
+                } else {
+                    (parent, id, "red")
+                };
+                statements.push_str(&format!(
+                    "    n{} -> n{} [label=\"{:?}\", color={}];\n",
+                    from, to, action, color,
+                ));
+            }
+
+            Some(Drawing::Step(statements))
+        }
+        // Drawn back to the node that already covers the state, outside of
+        // the tree layout.
+        SearchEvent::DuplicatePruned { id, parent } => Some(Drawing::Step(format!(
+            "    n{} -> n{} [style=dashed, color=grey, constraint=false];\n",
+            parent, id,
+        ))),
+        // Drawn in red, with the broken rule shown when hovering over it.
+        SearchEvent::SuccessorRejected {
+            id,
+            parent: (parent, action),
+            state,
+            reason,
+        } => {
+            let mut statements = format!(
+                "    n{} [label=\"{}\", tooltip=\"{}\", fillcolor=red, style=filled];\n",
+                id,
+                problem.label(state),
+                reason.replace('"', "\\\""),
+            );
+            statements.push_str(&format!(
+                "    n{} -> n{} [label=\"{:?}\", color=red, style=dotted];\n",
+                parent, id, action,
+            ));
+
+            Some(Drawing::Step(statements))
+        }
+        SearchEvent::NodeCutOff { id } => Some(Drawing::Amend(format!(
+            "    n{} [fillcolor=lightgrey];\n",
+            id
+        ))),
+        SearchEvent::GoalFound { id } => {
+            Some(Drawing::Amend(format!("    n{} [fillcolor=green];\n", id)))
+        }
+        SearchEvent::NodeExpanded { .. } | SearchEvent::FrontierSnapshot { .. } => None,
+    }
+}
+
+// Replays deltas into complete DOT graphs, building each frame only when the
+// iterator reaches it.
+pub fn frames(deltas: &[String]) -> impl Iterator<Item = String> + '_ {
+    let mut graph = String::new();
+
+    deltas.iter().map(move |delta| {
+        graph.push_str(delta);
+        format!("digraph {{\n{}}}", graph)
+    })
+}
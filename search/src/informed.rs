@@ -0,0 +1,371 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::Tracer,
+    path::Path,
+    tree::{Explored, Node},
+    Problem, SearchEvent, SearchObserver, SearchResult, SearchStats,
+};
+
+// A single f-threshold pass of `ida_star_search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdaStarIteration {
+    pub threshold: usize,
+    pub nodes_expanded: usize,
+    // Threshold for the following pass, `None` once the goal has been found
+    // or the space below every bound has been exhausted.
+    pub next_threshold: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Priority {
+    // f = g + h
+    PathCost,
+    // h alone
+    Heuristic,
+}
+
+// Best-first search with a binary heap open list. Stale heap entries are
+// skipped when popped (lazy deletion) instead of decreasing their key in
+// place. Goals are detected when they are expanded.
+fn best_first_search<P: Problem>(
+    problem: &P,
+    heuristic: impl Fn(&P::State) -> usize,
+    priority: Priority,
+    observer: &mut dyn SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    let rank = |g: usize, h: usize| match priority {
+        Priority::PathCost => g + h,
+        Priority::Heuristic => h,
+    };
+
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+    let mut tree: Explored<P> = Explored::new();
+
+    // Entries are ordered by rank, then h, then insertion order so that ties
+    // prefer nodes closer to the goal and otherwise behave like a queue. The
+    // insertion order also indexes `pushed`, which holds the entry's key.
+    let mut open: BinaryHeap<Reverse<(usize, usize, usize)>> = BinaryHeap::new();
+    let mut pushed: Vec<P::Key> = Vec::new();
+    let mut closed: HashSet<P::Key> = HashSet::new();
+
+    let initial = problem.initial_state();
+    let initial_key = problem.key(&initial);
+    let h = heuristic(&initial);
+
+    let initial_id = tracer.id(&initial_key);
+    tracer.emit(SearchEvent::NodeGenerated {
+        id: initial_id,
+        parent: None,
+        state: &initial,
+        depth: 0,
+        g: 0,
+        h: Some(h),
+        backward: false,
+    });
+
+    open.push(Reverse((rank(0, h), h, pushed.len())));
+    pushed.push(initial_key.clone());
+    stats.observe(open.len(), 1, problem.state_bytes(&initial));
+    tree.insert(
+        initial_key,
+        Node {
+            state: initial,
+            parent: None,
+            action: None,
+            depth: 0,
+            g: 0,
+            h,
+        },
+    );
+
+    let mut goal = None;
+
+    while let Some(Reverse((popped, _, order))) = open.pop() {
+        if tracer.interrupted() {
+            stats.finish(started, None);
+            return SearchResult::interrupted(stats);
+        }
+
+        let key = pushed[order].clone();
+        let current = tree.get(&key).unwrap();
+
+        if rank(current.g, current.h) != popped || closed.contains(&key) {
+            continue;
+        }
+
+        let id = tracer.id(&key);
+
+        if problem.is_goal(&current.state) {
+            tracer.emit(SearchEvent::GoalFound { id });
+            goal = Some(key);
+            break;
+        }
+
+        let (state, depth, g) = (current.state.clone(), current.depth, current.g);
+        let parent = tree.index_of(&key);
+
+        closed.insert(key.clone());
+        stats.nodes_expanded += 1;
+        tracer.expanded(problem, id, &state);
+
+        for (action, next, cost) in problem.successors(&state) {
+            stats.nodes_generated += 1;
+
+            let next_key = problem.key(&next);
+            let next_g = g + cost;
+
+            // Greedy search never reconsiders a state it has already seen.
+            if let Some(known) = tree.get(&next_key) {
+                if known.g <= next_g || priority == Priority::Heuristic {
+                    stats.duplicates_pruned += 1;
+                    let known = tracer.id(&next_key);
+                    tracer.emit(SearchEvent::DuplicatePruned {
+                        id: known,
+                        parent: id,
+                    });
+                    continue;
+                }
+            }
+
+            closed.remove(&next_key);
+
+            let next_h = heuristic(&next);
+
+            // A reopened node keeps its id and is reported again with the
+            // cheaper path.
+            let next_id = tracer.id(&next_key);
+            tracer.emit(SearchEvent::NodeGenerated {
+                id: next_id,
+                parent: Some((id, &action)),
+                state: &next,
+                depth: depth + 1,
+                g: next_g,
+                h: Some(next_h),
+                backward: false,
+            });
+
+            open.push(Reverse((rank(next_g, next_h), next_h, pushed.len())));
+            pushed.push(next_key.clone());
+
+            let node_bytes = problem.state_bytes(&next);
+            tree.insert(
+                next_key,
+                Node {
+                    state: next,
+                    parent,
+                    action: Some(action),
+                    depth: depth + 1,
+                    g: next_g,
+                    h: next_h,
+                },
+            );
+            stats.observe(open.len(), tree.len(), node_bytes);
+        }
+
+        tracer.emit(SearchEvent::FrontierSnapshot {
+            frontier: open.len(),
+            stored: tree.len(),
+        });
+    }
+
+    tree.result(goal.as_ref(), stats, started)
+}
+
+// Best-first search ordered by f = g + h. A closed node is reopened whenever a
+// cheaper path to it turns up, so the returned path is optimal for any
+// admissible heuristic, consistent or not.
+pub fn astar_search<P: Problem>(
+    problem: &P,
+    heuristic: impl Fn(&P::State) -> usize,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    best_first_search(problem, heuristic, Priority::PathCost, observer)
+}
+
+// Best-first search ordered by h alone, which heads straight for the goal and
+// usually expands far fewer nodes than A*, but may return a longer path.
+pub fn greedy_best_first_search<P: Problem>(
+    problem: &P,
+    heuristic: impl Fn(&P::State) -> usize,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    best_first_search(problem, heuristic, Priority::Heuristic, observer)
+}
+
+// A* without a heuristic, which expands nodes in order of path cost.
+pub fn uniform_cost_search<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    astar_search(problem, |_| 0, observer)
+}
+
+// One depth-first pass of IDA* bounded by `threshold`. Returns whether the
+// goal was reached, otherwise the smallest f value that exceeded the bound.
+// An interrupted pass gives up as if nothing exceeded it.
+fn ida_star_visit<P: Problem>(
+    problem: &P,
+    heuristic: &impl Fn(&P::State) -> usize,
+    threshold: usize,
+    path: &mut Path<P>,
+    stats: &mut SearchStats,
+    tracer: &mut Tracer<(P::Key, usize), P::Action, P::State>,
+) -> Result<(), usize> {
+    if problem.is_goal(path.last_state()) {
+        return Ok(());
+    }
+
+    if tracer.interrupted() {
+        return Err(usize::MAX);
+    }
+
+    stats.nodes_expanded += 1;
+
+    let mut next_threshold = usize::MAX;
+    let id = tracer.id(&(path.last_key().clone(), path.depth()));
+    tracer.expanded(problem, id, path.last_state());
+
+    for (action, next, cost) in problem.successors(path.last_state()) {
+        let next_key = problem.key(&next);
+
+        if let Some(depth) = path.position(&next_key) {
+            stats.duplicates_pruned += 1;
+            let known = tracer.id(&(next_key, depth));
+            tracer.emit(SearchEvent::DuplicatePruned {
+                id: known,
+                parent: id,
+            });
+            continue;
+        }
+
+        stats.nodes_generated += 1;
+
+        let g = path.g() + cost;
+        let h = heuristic(&next);
+        let f = g + h;
+
+        // Nodes are keyed by state and depth, so a state reached at several
+        // depths within one iteration gets a node for each. Paths that reach it
+        // at the same depth share a node, drawn with an edge from each parent.
+        let next_id = tracer.id(&(next_key.clone(), path.depth() + 1));
+        tracer.emit(SearchEvent::NodeGenerated {
+            id: next_id,
+            parent: Some((id, &action)),
+            state: &next,
+            depth: path.depth() + 1,
+            g,
+            h: Some(h),
+            backward: false,
+        });
+
+        if f > threshold {
+            tracer.emit(SearchEvent::NodeCutOff { id: next_id });
+            next_threshold = next_threshold.min(f);
+            continue;
+        }
+
+        if problem.is_goal(&next) {
+            tracer.emit(SearchEvent::GoalFound { id: next_id });
+        }
+
+        let node_bytes = problem.state_bytes(&next);
+        path.push(next_key, next, action, cost);
+        stats.observe(path.len(), path.len(), node_bytes);
+        tracer.emit(SearchEvent::FrontierSnapshot {
+            frontier: path.len(),
+            stored: path.len(),
+        });
+
+        match ida_star_visit(problem, heuristic, threshold, path, stats, tracer) {
+            Ok(()) => return Ok(()),
+            Err(exceeded) => next_threshold = next_threshold.min(exceeded),
+        }
+
+        path.pop();
+    }
+
+    Err(next_threshold)
+}
+
+// Iterative deepening A*: repeated depth-first searches bounded by an f
+// threshold, which starts at h(start) and grows to the smallest f that
+// exceeded it in the previous pass. Only the current path is kept in
+// memory. Each pass starts with an `IterationStarted` event.
+pub fn ida_star_search<P: Problem>(
+    problem: &P,
+    heuristic: impl Fn(&P::State) -> usize,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> (SearchResult<P::Action, P::State>, Vec<IdaStarIteration>) {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+
+    let initial = problem.initial_state();
+    let initial_id = tracer.id(&(problem.key(&initial), 0));
+
+    let mut iterations = Vec::new();
+    let mut threshold = heuristic(&initial);
+
+    loop {
+        let expanded_before = stats.nodes_expanded;
+
+        tracer.emit(SearchEvent::IterationStarted { bound: threshold });
+        tracer.emit(SearchEvent::NodeGenerated {
+            id: initial_id,
+            parent: None,
+            state: &initial,
+            depth: 0,
+            g: 0,
+            h: Some(heuristic(&initial)),
+            backward: false,
+        });
+
+        if problem.is_goal(&initial) {
+            tracer.emit(SearchEvent::GoalFound { id: initial_id });
+        }
+
+        stats.observe(1, 1, problem.state_bytes(&initial));
+        let mut path = Path::new(problem, initial.clone());
+        let visit = ida_star_visit(
+            problem,
+            &heuristic,
+            threshold,
+            &mut path,
+            &mut stats,
+            &mut tracer,
+        );
+
+        let next_threshold = match visit {
+            Err(exceeded) if exceeded != usize::MAX => Some(exceeded),
+            _ => None,
+        };
+
+        iterations.push(IdaStarIteration {
+            threshold,
+            nodes_expanded: stats.nodes_expanded - expanded_before,
+            next_threshold,
+        });
+
+        match (visit, next_threshold) {
+            (Ok(()), _) => return (path.into_result(stats, started), iterations),
+            (Err(_), _) if tracer.interrupted() => {
+                stats.finish(started, None);
+                return (SearchResult::interrupted(stats), iterations);
+            }
+            (Err(_), Some(next_threshold)) => threshold = next_threshold,
+            (Err(_), None) => {
+                stats.finish(started, None);
+                return (SearchResult::failure(stats), iterations);
+            }
+        }
+    }
+}
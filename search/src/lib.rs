@@ -0,0 +1,290 @@
+mod events;
+pub mod graphviz;
+mod informed;
+mod path;
+mod problem;
+mod result;
+mod stats;
+mod tree;
+mod uninformed;
+
+pub use events::{ExpansionLimit, SearchEvent, SearchObserver};
+pub use informed::{
+    astar_search, greedy_best_first_search, ida_star_search, uniform_cost_search, IdaStarIteration,
+};
+pub use problem::{Problem, ReversibleProblem};
+pub use result::SearchResult;
+pub use stats::{effective_branching_factor, SearchStats};
+pub use tree::{Node, SearchTree, TreeResult};
+pub use uninformed::{
+    all_simple_paths, bidirectional_breadth_first_search, breadth_first_search, breadth_first_tree,
+    depth_first_search, depth_first_tree, depth_limited_search, iterative_deepening_search,
+    DeepeningIteration, DepthLimitedOutcome, Solution, SolutionSet,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::{graphviz::Graphviz, *};
+
+    // An undirected weighted graph where the fewest hops from 0 to 4 go
+    // through the expensive edge 0 - 4, and the cheapest path is 0 1 2 3 4.
+    struct Graph {
+        edges: Vec<(u8, u8, usize)>,
+    }
+
+    impl Graph {
+        fn new() -> Graph {
+            Graph {
+                edges: vec![
+                    (0, 1, 1),
+                    (1, 2, 1),
+                    (2, 3, 1),
+                    (3, 4, 1),
+                    (0, 4, 10),
+                    (1, 5, 1),
+                ],
+            }
+        }
+    }
+
+    impl Problem for Graph {
+        type State = u8;
+        type Action = u8;
+        type Key = u8;
+
+        fn initial_state(&self) -> u8 {
+            0
+        }
+
+        fn successors(&self, state: &u8) -> Vec<(u8, u8, usize)> {
+            self.edges
+                .iter()
+                .filter_map(|&(a, b, cost)| match *state {
+                    s if s == a => Some((b, b, cost)),
+                    s if s == b => Some((a, a, cost)),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        fn is_goal(&self, state: &u8) -> bool {
+            *state == 4
+        }
+
+        fn key(&self, state: &u8) -> u8 {
+            *state
+        }
+
+        fn label(&self, state: &u8) -> String {
+            state.to_string()
+        }
+
+        fn rejected_successors(&self, state: &u8) -> Vec<(u8, u8, String)> {
+            match state {
+                0 => vec![(6, 6, "closed".to_string())],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    impl ReversibleProblem for Graph {
+        fn goal_state(&self) -> u8 {
+            4
+        }
+
+        fn predecessors(&self, state: &u8) -> Vec<(u8, u8, usize)> {
+            self.successors(state)
+                .into_iter()
+                .map(|(_, previous, cost)| (*state, previous, cost))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_blind_searches() {
+        let graph = Graph::new();
+
+        let result = breadth_first_search(&graph, &mut ());
+        assert_eq!(result.states, vec![0, 4]);
+        assert_eq!(result.cost, 10);
+
+        let result = depth_first_search(&graph, &mut ());
+        assert!(result.found);
+        assert_eq!(result.states.last(), Some(&4));
+
+        let (result, outcome) = depth_limited_search(&graph, 0, &mut ());
+        assert_eq!(outcome, DepthLimitedOutcome::Cutoff);
+        assert!(!result.found);
+
+        let (result, iterations) = iterative_deepening_search(&graph, 10, &mut ());
+        assert_eq!(result.actions, vec![4]);
+        assert_eq!(iterations.len(), 2);
+
+        let result = bidirectional_breadth_first_search(&graph, &mut ());
+        assert_eq!(result.states, vec![0, 4]);
+        assert_eq!(result.actions, vec![4]);
+    }
+
+    #[test]
+    fn test_all_simple_paths() {
+        let solutions = all_simple_paths(&Graph::new(), &mut ());
+
+        let mut paths: Vec<(usize, Vec<u8>)> = solutions
+            .solutions
+            .into_iter()
+            .map(|solution| (solution.cost, solution.states))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec![(4, vec![0, 1, 2, 3, 4]), (10, vec![0, 4])]);
+        assert_eq!(solutions.optimal, 1);
+        assert_eq!(solutions.stats.solution_depth, Some(4));
+    }
+
+    #[test]
+    fn test_search_tree() {
+        let (result, tree) = breadth_first_tree(&Graph::new(), &mut ());
+        assert_eq!(result.states, vec![0, 4]);
+
+        // Every parent comes before its children, and the goal was the last
+        // node generated.
+        let goal = tree.len() - 1;
+        assert!(tree
+            .nodes()
+            .iter()
+            .enumerate()
+            .all(|(index, node)| node.parent.is_none_or(|parent| parent < index)));
+        assert_eq!(tree.ancestors(goal).collect::<Vec<_>>(), vec![goal, 0]);
+        assert_eq!(tree.path_to(goal), (result.actions, result.states));
+
+        // Goals are detected when they are generated, so the goal is the
+        // last node here as well.
+        let (result, tree) = depth_first_tree(&Graph::new(), &mut ());
+        assert!(result.found);
+        assert_eq!(tree.len(), 3);
+
+        let tree = tree.map_states(|state| state.to_string());
+        assert_eq!(tree.get(2).map(|node| node.state.as_str()), Some("4"));
+        assert_eq!(tree.get(2).and_then(|node| node.action), Some(4));
+    }
+
+    #[test]
+    fn test_cost_aware_searches() {
+        let graph = Graph::new();
+
+        let result = uniform_cost_search(&graph, &mut ());
+        assert_eq!(result.states, vec![0, 1, 2, 3, 4]);
+        assert_eq!(result.cost, 4);
+
+        let distance = |state: &u8| match state {
+            0 => 4,
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => 0,
+        };
+
+        let result = astar_search(&graph, distance, &mut ());
+        assert_eq!(result.cost, 4);
+
+        // Node 4 looks closest, so greedy search takes the expensive edge.
+        let result = greedy_best_first_search(&graph, distance, &mut ());
+        assert_eq!(result.states, vec![0, 4]);
+        assert_eq!(result.cost, 10);
+
+        let (result, iterations) = ida_star_search(&graph, distance, &mut ());
+        assert_eq!(result.cost, 4);
+        assert_eq!(iterations.last().unwrap().threshold, 4);
+    }
+
+    #[test]
+    fn test_event_stream() {
+        let graph = Graph::new();
+
+        let mut kinds = Vec::new();
+        let result = breadth_first_search(&graph, &mut |event: SearchEvent<'_, u8, u8>| {
+            kinds.push(match event {
+                SearchEvent::NodeGenerated { id, parent, .. } => {
+                    format!("generated {} from {:?}", id, parent.map(|(p, _)| p))
+                }
+                SearchEvent::NodeExpanded { id } => format!("expanded {}", id),
+                SearchEvent::SuccessorRejected {
+                    id, parent, reason, ..
+                } => format!("rejected {} from {}: {}", id, parent.0, reason),
+                SearchEvent::GoalFound { id } => format!("goal {}", id),
+                _ => return,
+            })
+        });
+        assert!(result.found);
+        assert_eq!(
+            kinds,
+            vec![
+                "generated 0 from None",
+                "expanded 0",
+                "rejected 1 from 0: closed",
+                "generated 2 from Some(0)",
+                "generated 3 from Some(0)",
+                "goal 3",
+            ]
+        );
+
+        // Each side of a bidirectional search numbers its own nodes, so the
+        // state where they meet has a node in both trees.
+        let mut generated = Vec::new();
+        let mut goals = Vec::new();
+        bidirectional_breadth_first_search(
+            &graph,
+            &mut |event: SearchEvent<'_, u8, u8>| match event {
+                SearchEvent::NodeGenerated {
+                    id,
+                    state,
+                    backward,
+                    ..
+                } => generated.push((id, *state, backward)),
+                SearchEvent::GoalFound { id } => goals.push(id),
+                _ => {}
+            },
+        );
+        assert_eq!(
+            generated,
+            vec![(0, 0, false), (1, 4, true), (2, 1, false), (3, 4, false)]
+        );
+        assert_eq!(goals, vec![3]);
+
+        // One delta per generated node, and every iteration drawn separately.
+        let mut graphviz = Graphviz::new(&graph);
+        bidirectional_breadth_first_search(&graph, &mut graphviz);
+        let deltas = graphviz.into_deltas();
+        assert_eq!(deltas.len(), 1);
+        let last = graphviz::frames(&deltas[0]).last().unwrap();
+        assert!(last.starts_with("digraph {\n") && last.contains("fillcolor=green"));
+
+        // Rejected successors are drawn in red, and states met again as
+        // dashed edges back to their first node.
+        let mut graphviz = Graphviz::new(&graph);
+        uniform_cost_search(&graph, &mut graphviz);
+        let last = graphviz::frames(&graphviz.into_deltas()[0]).last().unwrap();
+        assert!(last.contains("n1 [label=\"6\", tooltip=\"closed\", fillcolor=red"));
+        assert!(last.contains("style=dashed"));
+
+        let mut graphviz = Graphviz::new(&graph);
+        let (_, iterations) = ida_star_search(&graph, |_| 0, &mut graphviz);
+        assert_eq!(graphviz.into_deltas().len(), iterations.len());
+    }
+
+    #[test]
+    fn test_expansion_limit_interrupts_search() {
+        let graph = Graph::new();
+
+        let result = uniform_cost_search(&graph, &mut ExpansionLimit::new(&mut (), 2));
+        assert!(result.interrupted && !result.found);
+        assert_eq!(result.stats.nodes_expanded, 2);
+
+        let (result, iterations) =
+            ida_star_search(&graph, |_| 0, &mut ExpansionLimit::new(&mut (), 3));
+        assert!(result.interrupted);
+        assert!(iterations.len() < 5);
+
+        let result = breadth_first_search(&graph, &mut ExpansionLimit::new(&mut (), 10));
+        assert!(result.found && !result.interrupted);
+    }
+}
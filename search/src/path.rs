@@ -0,0 +1,89 @@
+use std::time::Instant;
+
+use crate::{Problem, SearchResult, SearchStats, Solution};
+
+// The current branch of a depth-first search, from the initial state down to
+// the node being visited.
+pub(crate) struct Path<P: Problem> {
+    keys: Vec<P::Key>,
+    states: Vec<P::State>,
+    actions: Vec<P::Action>,
+    // Path cost up to and including each state.
+    costs: Vec<usize>,
+}
+
+impl<P: Problem> Path<P> {
+    pub(crate) fn new(problem: &P, state: P::State) -> Path<P> {
+        Path {
+            keys: vec![problem.key(&state)],
+            states: vec![state],
+            actions: Vec::new(),
+            costs: vec![0],
+        }
+    }
+
+    // Depth at which `key` appears on the path.
+    pub(crate) fn position(&self, key: &P::Key) -> Option<usize> {
+        self.keys.iter().position(|known| known == key)
+    }
+
+    pub(crate) fn push(&mut self, key: P::Key, state: P::State, action: P::Action, cost: usize) {
+        self.costs.push(self.g() + cost);
+        self.keys.push(key);
+        self.states.push(state);
+        self.actions.push(action);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.keys.pop();
+        self.states.pop();
+        self.actions.pop();
+        self.costs.pop();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    // Number of actions taken to reach the last state.
+    pub(crate) fn depth(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub(crate) fn g(&self) -> usize {
+        *self.costs.last().unwrap()
+    }
+
+    pub(crate) fn last_key(&self) -> &P::Key {
+        self.keys.last().unwrap()
+    }
+
+    pub(crate) fn last_state(&self) -> &P::State {
+        self.states.last().unwrap()
+    }
+
+    pub(crate) fn to_solution(&self) -> Solution<P::Action, P::State> {
+        Solution {
+            actions: self.actions.clone(),
+            states: self.states.clone(),
+            cost: self.g(),
+        }
+    }
+
+    pub(crate) fn into_result(
+        self,
+        mut stats: SearchStats,
+        started: Instant,
+    ) -> SearchResult<P::Action, P::State> {
+        stats.finish(started, Some(self.actions.len()));
+
+        SearchResult {
+            found: true,
+            interrupted: false,
+            cost: self.g(),
+            actions: self.actions,
+            states: self.states,
+            stats,
+        }
+    }
+}
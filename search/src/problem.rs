@@ -0,0 +1,48 @@
+use std::{fmt::Debug, hash::Hash, mem::size_of};
+
+// A state space that the searches in this crate can explore.
+pub trait Problem {
+    type State: Clone;
+    type Action: Clone + Debug;
+    // Identifies states for duplicate detection, two states with the same key
+    // are treated as the same node.
+    type Key: Clone + Eq + Hash;
+
+    fn initial_state(&self) -> Self::State;
+
+    // Every state reachable in one step, with the action that leads to it and
+    // the cost of taking it.
+    fn successors(&self, state: &Self::State) -> Vec<(Self::Action, Self::State, usize)>;
+
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    fn key(&self, state: &Self::State) -> Self::Key;
+
+    // Node label in the graphviz frames. `\\n` may be used for line breaks.
+    fn label(&self, state: &Self::State) -> String;
+
+    // Successors that the rules of the problem forbid, with the rule each one
+    // breaks. They are never searched, only reported to observers so that
+    // dead ends can be shown.
+    fn rejected_successors(
+        &self,
+        _state: &Self::State,
+    ) -> Vec<(Self::Action, Self::State, String)> {
+        Vec::new()
+    }
+
+    // Approximate size of one stored state, used for the memory estimate.
+    fn state_bytes(&self, _state: &Self::State) -> usize {
+        size_of::<Self::State>()
+    }
+}
+
+// A problem with a single known goal state that can also be searched
+// backwards from that goal.
+pub trait ReversibleProblem: Problem {
+    fn goal_state(&self) -> Self::State;
+
+    // Every state that reaches `state` in one step, with the action that leads
+    // from the predecessor to `state` and its cost.
+    fn predecessors(&self, state: &Self::State) -> Vec<(Self::Action, Self::State, usize)>;
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::SearchStats;
+
+// Outcome of a search, with the solution as the actions taken and every
+// state along the way, from the initial state to the goal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult<A, S> {
+    pub found: bool,
+    // The observer stopped the search before it could finish.
+    pub interrupted: bool,
+    pub actions: Vec<A>,
+    pub states: Vec<S>,
+    pub cost: usize,
+    pub stats: SearchStats,
+}
+
+impl<A, S> SearchResult<A, S> {
+    pub(crate) fn failure(stats: SearchStats) -> SearchResult<A, S> {
+        SearchResult {
+            found: false,
+            interrupted: false,
+            actions: Vec::new(),
+            states: Vec::new(),
+            cost: 0,
+            stats,
+        }
+    }
+
+    pub(crate) fn interrupted(stats: SearchStats) -> SearchResult<A, S> {
+        SearchResult {
+            interrupted: true,
+            ..SearchResult::failure(stats)
+        }
+    }
+
+    pub fn map_states<T>(self, f: impl FnMut(S) -> T) -> SearchResult<A, T> {
+        SearchResult {
+            found: self.found,
+            interrupted: self.interrupted,
+            actions: self.actions,
+            states: self.states.into_iter().map(f).collect(),
+            cost: self.cost,
+            stats: self.stats,
+        }
+    }
+}
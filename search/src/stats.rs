@@ -0,0 +1,71 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchStats {
+    pub nodes_generated: usize,
+    pub nodes_expanded: usize,
+    pub duplicates_pruned: usize,
+    pub max_frontier: usize,
+    // Estimated from the largest number of nodes held at once, the frontier
+    // plus the visited set for graph searches or the current path for the
+    // depth-first ones, at the size of the node stored last.
+    pub peak_memory_bytes: usize,
+    pub elapsed_ms: f64,
+    pub solution_depth: Option<usize>,
+    pub effective_branching_factor: Option<f64>,
+}
+
+impl SearchStats {
+    pub(crate) fn observe(&mut self, frontier: usize, stored: usize, node_bytes: usize) {
+        self.max_frontier = self.max_frontier.max(frontier);
+        self.peak_memory_bytes = self.peak_memory_bytes.max(stored * node_bytes);
+    }
+
+    pub(crate) fn finish(&mut self, started: Instant, solution_depth: Option<usize>) {
+        self.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.solution_depth = solution_depth;
+        self.effective_branching_factor = solution_depth
+            .and_then(|depth| effective_branching_factor(self.nodes_generated, depth));
+    }
+}
+
+// The branching factor b* that a uniform tree of depth `depth` would need to
+// hold `nodes_generated + 1` nodes, i.e. the root of
+// 1 + b + b^2 + ... + b^depth = nodes_generated + 1, found by bisection.
+pub fn effective_branching_factor(nodes_generated: usize, depth: usize) -> Option<f64> {
+    if depth == 0 {
+        return None;
+    }
+
+    let total = nodes_generated as f64 + 1.0;
+    let tree_size = |b: f64| (0..=depth).map(|i| b.powi(i as i32)).sum::<f64>();
+
+    let mut low = 0.0;
+    let mut high = total.max(1.0);
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if tree_size(middle) < total {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_branching_factor() {
+        // A complete binary tree of depth 3 has 1 + 2 + 4 + 8 nodes.
+        let b = effective_branching_factor(14, 3).unwrap();
+        assert!((b - 2.0).abs() < 1e-6);
+
+        assert_eq!(effective_branching_factor(5, 0), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Problem, SearchResult, SearchStats};
+
+// A node of a search tree, which refers to its parent by index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node<A, S> {
+    pub state: S,
+    // The node this one was generated from and the action taken there, both
+    // `None` for the root.
+    pub parent: Option<usize>,
+    pub action: Option<A>,
+    pub depth: usize,
+    pub g: usize,
+    pub h: usize,
+}
+
+// Every node a search has kept, in the order they were generated. Parents
+// always come before their children, so the tree can be walked upwards from
+// any node and serialized as one flat list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchTree<A, S> {
+    nodes: Vec<Node<A, S>>,
+}
+
+// A search result along with the tree the search built.
+pub type TreeResult<A, S> = (SearchResult<A, S>, SearchTree<A, S>);
+
+impl<A, S> Default for SearchTree<A, S> {
+    fn default() -> SearchTree<A, S> {
+        SearchTree { nodes: Vec::new() }
+    }
+}
+
+impl<A: Clone, S: Clone> SearchTree<A, S> {
+    pub fn new() -> SearchTree<A, S> {
+        SearchTree::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn nodes(&self) -> &[Node<A, S>] {
+        &self.nodes
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Node<A, S>> {
+        self.nodes.get(index)
+    }
+
+    pub(crate) fn push(&mut self, node: Node<A, S>) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    // Indices of `index` and every node above it, up to the root.
+    pub fn ancestors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(index), |&index| self.nodes[index].parent)
+    }
+
+    // Actions and states from the root down to `index`.
+    pub fn path_to(&self, index: usize) -> (Vec<A>, Vec<S>) {
+        let mut actions = Vec::new();
+        let mut states = Vec::new();
+
+        for ancestor in self.ancestors(index) {
+            let node = &self.nodes[ancestor];
+            states.push(node.state.clone());
+            actions.extend(node.action.clone());
+        }
+
+        actions.reverse();
+        states.reverse();
+        (actions, states)
+    }
+
+    pub fn map_states<T>(self, mut f: impl FnMut(S) -> T) -> SearchTree<A, T> {
+        SearchTree {
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|node| Node {
+                    state: f(node.state),
+                    parent: node.parent,
+                    action: node.action,
+                    depth: node.depth,
+                    g: node.g,
+                    h: node.h,
+                })
+                .collect(),
+        }
+    }
+}
+
+// A search tree together with the node that currently stands for every
+// state, which is how the graph searches detect duplicates. A state that is
+// reached again along a cheaper path gets a new node.
+pub(crate) struct Explored<P: Problem> {
+    pub(crate) tree: SearchTree<P::Action, P::State>,
+    index: HashMap<P::Key, usize>,
+}
+
+impl<P: Problem> Explored<P> {
+    pub(crate) fn new() -> Explored<P> {
+        Explored {
+            tree: SearchTree::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    // Number of distinct states.
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub(crate) fn index_of(&self, key: &P::Key) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    pub(crate) fn get(&self, key: &P::Key) -> Option<&Node<P::Action, P::State>> {
+        self.index_of(key).map(|index| &self.tree.nodes[index])
+    }
+
+    pub(crate) fn contains(&self, key: &P::Key) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub(crate) fn insert(&mut self, key: P::Key, node: Node<P::Action, P::State>) -> usize {
+        let index = self.tree.push(node);
+        self.index.insert(key, index);
+        index
+    }
+
+    pub(crate) fn result(
+        &self,
+        goal: Option<&P::Key>,
+        mut stats: SearchStats,
+        started: Instant,
+    ) -> SearchResult<P::Action, P::State> {
+        let goal = match goal.and_then(|goal| self.index_of(goal)) {
+            Some(goal) => goal,
+            None => {
+                stats.finish(started, None);
+                return SearchResult::failure(stats);
+            }
+        };
+
+        let (actions, states) = self.tree.path_to(goal);
+        stats.finish(started, Some(actions.len()));
+
+        SearchResult {
+            found: true,
+            interrupted: false,
+            cost: self.tree.nodes[goal].g,
+            actions,
+            states,
+            stats,
+        }
+    }
+}
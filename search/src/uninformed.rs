@@ -0,0 +1,728 @@
+use std::{collections::VecDeque, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::Tracer,
+    path::Path,
+    tree::{Explored, Node, TreeResult},
+    Problem, ReversibleProblem, SearchEvent, SearchObserver, SearchResult, SearchStats,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepthLimitedOutcome {
+    Solution,
+    // The depth limit was reached on some branch, a deeper search may succeed.
+    Cutoff,
+    // Every branch was exhausted within the limit.
+    Failure,
+}
+
+// A single depth limit pass of `iterative_deepening_search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeepeningIteration {
+    pub limit: usize,
+    pub outcome: DepthLimitedOutcome,
+    pub nodes_expanded: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Frontier {
+    Queue,
+    Stack,
+}
+
+// Shared loop of the breadth-first and depth-first graph searches. Goals are
+// detected as soon as they are generated, and a state is never added to the
+// frontier twice, so the tree holds one node per state.
+fn graph_search<P: Problem>(
+    problem: &P,
+    frontier_kind: Frontier,
+    observer: &mut dyn SearchObserver<P::Action, P::State>,
+) -> TreeResult<P::Action, P::State> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+    let mut explored: Explored<P> = Explored::new();
+
+    let initial = problem.initial_state();
+    let initial_key = problem.key(&initial);
+    let initial_id = tracer.id(&initial_key);
+
+    tracer.emit(SearchEvent::NodeGenerated {
+        id: initial_id,
+        parent: None,
+        state: &initial,
+        depth: 0,
+        g: 0,
+        h: None,
+        backward: false,
+    });
+
+    let is_goal = problem.is_goal(&initial);
+    stats.observe(1, 1, problem.state_bytes(&initial));
+    explored.insert(
+        initial_key.clone(),
+        Node {
+            state: initial,
+            parent: None,
+            action: None,
+            depth: 0,
+            g: 0,
+            h: 0,
+        },
+    );
+
+    if is_goal {
+        tracer.emit(SearchEvent::GoalFound { id: initial_id });
+        let result = explored.result(Some(&initial_key), stats, started);
+        return (result, explored.tree);
+    }
+
+    let mut frontier = VecDeque::from([initial_key]);
+
+    loop {
+        if tracer.interrupted() {
+            stats.finish(started, None);
+            return (SearchResult::interrupted(stats), explored.tree);
+        }
+
+        let key = match frontier_kind {
+            Frontier::Queue => frontier.pop_front(),
+            Frontier::Stack => frontier.pop_back(),
+        };
+        let key = match key {
+            Some(key) => key,
+            None => break,
+        };
+
+        stats.nodes_expanded += 1;
+
+        let id = tracer.id(&key);
+        let parent = explored.index_of(&key).unwrap();
+        let current = &explored.tree.nodes()[parent];
+        let (state, depth, g) = (current.state.clone(), current.depth, current.g);
+        tracer.expanded(problem, id, &state);
+
+        for (action, next, cost) in problem.successors(&state) {
+            stats.nodes_generated += 1;
+
+            let next_key = problem.key(&next);
+
+            if explored.contains(&next_key) {
+                stats.duplicates_pruned += 1;
+                let known = tracer.id(&next_key);
+                tracer.emit(SearchEvent::DuplicatePruned {
+                    id: known,
+                    parent: id,
+                });
+                continue;
+            }
+
+            let next_id = tracer.id(&next_key);
+            tracer.emit(SearchEvent::NodeGenerated {
+                id: next_id,
+                parent: Some((id, &action)),
+                state: &next,
+                depth: depth + 1,
+                g: g + cost,
+                h: None,
+                backward: false,
+            });
+
+            let is_goal = problem.is_goal(&next);
+            let node_bytes = problem.state_bytes(&next);
+            explored.insert(
+                next_key.clone(),
+                Node {
+                    state: next,
+                    parent: Some(parent),
+                    action: Some(action),
+                    depth: depth + 1,
+                    g: g + cost,
+                    h: 0,
+                },
+            );
+
+            if is_goal {
+                tracer.emit(SearchEvent::GoalFound { id: next_id });
+                let result = explored.result(Some(&next_key), stats, started);
+                return (result, explored.tree);
+            }
+
+            frontier.push_back(next_key);
+            stats.observe(frontier.len(), explored.len(), node_bytes);
+        }
+
+        tracer.emit(SearchEvent::FrontierSnapshot {
+            frontier: frontier.len(),
+            stored: explored.len(),
+        });
+    }
+
+    (explored.result(None, stats, started), explored.tree)
+}
+
+pub fn breadth_first_search<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    graph_search(problem, Frontier::Queue, observer).0
+}
+
+pub fn depth_first_search<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    graph_search(problem, Frontier::Stack, observer).0
+}
+
+// Breadth-first search that also returns every node it generated, which is
+// the whole reachable space when there is no goal in it.
+pub fn breadth_first_tree<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> TreeResult<P::Action, P::State> {
+    graph_search(problem, Frontier::Queue, observer)
+}
+
+pub fn depth_first_tree<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> TreeResult<P::Action, P::State> {
+    graph_search(problem, Frontier::Stack, observer)
+}
+
+// A single path found by `all_simple_paths`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Solution<A, S> {
+    pub actions: Vec<A>,
+    pub states: Vec<S>,
+    pub cost: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolutionSet<A, S> {
+    // In the order the depth-first enumeration found them.
+    pub solutions: Vec<Solution<A, S>>,
+    // How many solutions share the lowest cost.
+    pub optimal: usize,
+    // The observer stopped the enumeration, so some solutions may be missing.
+    pub interrupted: bool,
+    pub stats: SearchStats,
+}
+
+// Recursive step of `all_simple_paths`. `ids` holds the node id of every
+// state on the path.
+fn simple_paths_visit<P: Problem>(
+    problem: &P,
+    path: &mut Path<P>,
+    ids: &mut Vec<usize>,
+    solutions: &mut Vec<Solution<P::Action, P::State>>,
+    stats: &mut SearchStats,
+    tracer: &mut Tracer<usize, P::Action, P::State>,
+) {
+    if tracer.interrupted() {
+        return;
+    }
+
+    stats.nodes_expanded += 1;
+
+    let id = *ids.last().unwrap();
+    tracer.expanded(problem, id, path.last_state());
+
+    for (action, next, cost) in problem.successors(path.last_state()) {
+        let next_key = problem.key(&next);
+
+        if let Some(depth) = path.position(&next_key) {
+            stats.duplicates_pruned += 1;
+            tracer.emit(SearchEvent::DuplicatePruned {
+                id: ids[depth],
+                parent: id,
+            });
+            continue;
+        }
+
+        stats.nodes_generated += 1;
+
+        // A state reached along another path is drawn as a node of its own.
+        let next_id = tracer.id(&stats.nodes_generated);
+        tracer.emit(SearchEvent::NodeGenerated {
+            id: next_id,
+            parent: Some((id, &action)),
+            state: &next,
+            depth: path.depth() + 1,
+            g: path.g() + cost,
+            h: None,
+            backward: false,
+        });
+
+        let is_goal = problem.is_goal(&next);
+        let node_bytes = problem.state_bytes(&next);
+        path.push(next_key, next, action, cost);
+        ids.push(next_id);
+        stats.observe(path.len(), path.len(), node_bytes);
+
+        // Paths end at the first goal on them.
+        if is_goal {
+            tracer.emit(SearchEvent::GoalFound { id: next_id });
+            solutions.push(path.to_solution());
+        } else {
+            simple_paths_visit(problem, path, ids, solutions, stats, tracer);
+        }
+
+        ids.pop();
+        path.pop();
+
+        if tracer.interrupted() {
+            return;
+        }
+    }
+}
+
+// Enumerates every path from the initial state to a goal that visits no
+// state twice, by a depth-first search over the whole tree of such paths.
+// This grows exponentially with the size of the state space, so it is only
+// meant for small problems.
+pub fn all_simple_paths<P: Problem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SolutionSet<P::Action, P::State> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+    let mut solutions = Vec::new();
+
+    let initial = problem.initial_state();
+    let initial_id = tracer.id(&0);
+
+    tracer.emit(SearchEvent::NodeGenerated {
+        id: initial_id,
+        parent: None,
+        state: &initial,
+        depth: 0,
+        g: 0,
+        h: None,
+        backward: false,
+    });
+
+    stats.observe(1, 1, problem.state_bytes(&initial));
+    let is_goal = problem.is_goal(&initial);
+    let mut path = Path::new(problem, initial);
+
+    if is_goal {
+        tracer.emit(SearchEvent::GoalFound { id: initial_id });
+        solutions.push(path.to_solution());
+    } else {
+        let mut ids = vec![initial_id];
+        simple_paths_visit(
+            problem,
+            &mut path,
+            &mut ids,
+            &mut solutions,
+            &mut stats,
+            &mut tracer,
+        );
+    }
+
+    let lowest = solutions.iter().map(|solution| solution.cost).min();
+    let optimal = solutions
+        .iter()
+        .filter(|solution| Some(solution.cost) == lowest)
+        .count();
+    stats.finish(
+        started,
+        solutions
+            .iter()
+            .find(|solution| Some(solution.cost) == lowest)
+            .map(|solution| solution.actions.len()),
+    );
+
+    SolutionSet {
+        solutions,
+        optimal,
+        interrupted: tracer.interrupted(),
+        stats,
+    }
+}
+
+// Recursive step of `depth_limited_search`. Only states on the current path
+// are skipped, so a state first met deep in the tree is still explored again
+// when it is reached through a shallower path.
+fn depth_limited_visit<P: Problem>(
+    problem: &P,
+    limit: usize,
+    path: &mut Path<P>,
+    stats: &mut SearchStats,
+    tracer: &mut Tracer<(P::Key, usize), P::Action, P::State>,
+) -> DepthLimitedOutcome {
+    if problem.is_goal(path.last_state()) {
+        return DepthLimitedOutcome::Solution;
+    }
+
+    // An interrupted pass has not explored everything below the limit either.
+    if path.depth() >= limit || tracer.interrupted() {
+        return DepthLimitedOutcome::Cutoff;
+    }
+
+    stats.nodes_expanded += 1;
+
+    let mut cutoff_occurred = false;
+    let id = tracer.id(&(path.last_key().clone(), path.depth()));
+    tracer.expanded(problem, id, path.last_state());
+
+    for (action, next, cost) in problem.successors(path.last_state()) {
+        let next_key = problem.key(&next);
+
+        if let Some(depth) = path.position(&next_key) {
+            stats.duplicates_pruned += 1;
+            let known = tracer.id(&(next_key, depth));
+            tracer.emit(SearchEvent::DuplicatePruned {
+                id: known,
+                parent: id,
+            });
+            continue;
+        }
+
+        stats.nodes_generated += 1;
+
+        // Nodes are keyed by state and depth, so a state reached at several
+        // depths within one pass gets a node for each. Paths that reach it
+        // at the same depth share a node, drawn with an edge from each parent.
+        let next_id = tracer.id(&(next_key.clone(), path.depth() + 1));
+        tracer.emit(SearchEvent::NodeGenerated {
+            id: next_id,
+            parent: Some((id, &action)),
+            state: &next,
+            depth: path.depth() + 1,
+            g: path.g() + cost,
+            h: None,
+            backward: false,
+        });
+
+        if problem.is_goal(&next) {
+            tracer.emit(SearchEvent::GoalFound { id: next_id });
+        } else if path.depth() + 1 >= limit {
+            tracer.emit(SearchEvent::NodeCutOff { id: next_id });
+        }
+
+        let node_bytes = problem.state_bytes(&next);
+        path.push(next_key, next, action, cost);
+        stats.observe(path.len(), path.len(), node_bytes);
+        tracer.emit(SearchEvent::FrontierSnapshot {
+            frontier: path.len(),
+            stored: path.len(),
+        });
+
+        match depth_limited_visit(problem, limit, path, stats, tracer) {
+            DepthLimitedOutcome::Solution => return DepthLimitedOutcome::Solution,
+            DepthLimitedOutcome::Cutoff => cutoff_occurred = true,
+            DepthLimitedOutcome::Failure => {}
+        }
+
+        path.pop();
+    }
+
+    if cutoff_occurred {
+        DepthLimitedOutcome::Cutoff
+    } else {
+        DepthLimitedOutcome::Failure
+    }
+}
+
+// One pass of depth-limited search. The path is left ending at the goal when
+// one is found.
+fn depth_limited_pass<P: Problem>(
+    problem: &P,
+    limit: usize,
+    stats: &mut SearchStats,
+    tracer: &mut Tracer<(P::Key, usize), P::Action, P::State>,
+) -> (Path<P>, DeepeningIteration) {
+    let expanded_before = stats.nodes_expanded;
+
+    let initial = problem.initial_state();
+    let initial_id = tracer.id(&(problem.key(&initial), 0));
+
+    tracer.emit(SearchEvent::IterationStarted { bound: limit });
+    tracer.emit(SearchEvent::NodeGenerated {
+        id: initial_id,
+        parent: None,
+        state: &initial,
+        depth: 0,
+        g: 0,
+        h: None,
+        backward: false,
+    });
+
+    if problem.is_goal(&initial) {
+        tracer.emit(SearchEvent::GoalFound { id: initial_id });
+    }
+
+    stats.observe(1, 1, problem.state_bytes(&initial));
+    let mut path = Path::new(problem, initial);
+    let outcome = depth_limited_visit(problem, limit, &mut path, stats, tracer);
+
+    (
+        path,
+        DeepeningIteration {
+            limit,
+            outcome,
+            nodes_expanded: stats.nodes_expanded - expanded_before,
+        },
+    )
+}
+
+// Depth-first search that does not expand nodes deeper than `limit`.
+// Reports `Cutoff` when the limit stopped it from exploring some branch,
+// and `Failure` when no solution exists at any depth.
+pub fn depth_limited_search<P: Problem>(
+    problem: &P,
+    limit: usize,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> (SearchResult<P::Action, P::State>, DepthLimitedOutcome) {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+
+    let (path, iteration) = depth_limited_pass(problem, limit, &mut stats, &mut tracer);
+
+    let result = if iteration.outcome == DepthLimitedOutcome::Solution {
+        path.into_result(stats, started)
+    } else if tracer.interrupted() {
+        stats.finish(started, None);
+        SearchResult::interrupted(stats)
+    } else {
+        stats.finish(started, None);
+        SearchResult::failure(stats)
+    };
+
+    (result, iteration.outcome)
+}
+
+// Runs `depth_limited_search` with limits 0, 1, 2, ... up to `max_limit`,
+// stopping at the first solution or once a pass fails without a cutoff.
+// Each depth starts with an `IterationStarted` event.
+pub fn iterative_deepening_search<P: Problem>(
+    problem: &P,
+    max_limit: usize,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> (SearchResult<P::Action, P::State>, Vec<DeepeningIteration>) {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+
+    let mut iterations: Vec<DeepeningIteration> = Vec::new();
+
+    for limit in 0..=max_limit {
+        let (path, iteration) = depth_limited_pass(problem, limit, &mut stats, &mut tracer);
+        let outcome = iteration.outcome;
+        iterations.push(iteration);
+
+        match outcome {
+            DepthLimitedOutcome::Solution => {
+                return (path.into_result(stats, started), iterations);
+            }
+            DepthLimitedOutcome::Failure => break,
+            DepthLimitedOutcome::Cutoff if tracer.interrupted() => {
+                stats.finish(started, None);
+                return (SearchResult::interrupted(stats), iterations);
+            }
+            DepthLimitedOutcome::Cutoff => {}
+        }
+    }
+
+    stats.finish(started, None);
+    (SearchResult::failure(stats), iterations)
+}
+
+// Breadth-first search from both the initial and the goal state at once,
+// expanding a whole layer of the smaller frontier at a time until the two
+// meet. Nodes of the backward tree are reported with `backward` set, and the
+// meeting state as the goal.
+pub fn bidirectional_breadth_first_search<P: ReversibleProblem>(
+    problem: &P,
+    observer: &mut impl SearchObserver<P::Action, P::State>,
+) -> SearchResult<P::Action, P::State> {
+    let started = Instant::now();
+    let mut stats = SearchStats::default();
+    let mut tracer = Tracer::new(observer);
+
+    let initial = problem.initial_state();
+    let goal = problem.goal_state();
+    let initial_key = problem.key(&initial);
+    let goal_key = problem.key(&goal);
+
+    // Each side numbers its own nodes, so a state reached from both sides
+    // gets a node in either tree and the drawing shows the two trees meet.
+    for (key, state, backward) in [(&initial_key, &initial, false), (&goal_key, &goal, true)] {
+        let id = tracer.id(&(backward, key.clone()));
+        tracer.emit(SearchEvent::NodeGenerated {
+            id,
+            parent: None,
+            state,
+            depth: 0,
+            g: 0,
+            h: None,
+            backward,
+        });
+    }
+
+    // On the backward side the parent link points towards the goal, with the
+    // action that leads from the node to that parent.
+    let mut trees: [Explored<P>; 2] = [Explored::new(), Explored::new()];
+    for (side, (key, state)) in [(initial_key.clone(), initial), (goal_key.clone(), goal)]
+        .into_iter()
+        .enumerate()
+    {
+        trees[side].insert(
+            key,
+            Node {
+                state,
+                parent: None,
+                action: None,
+                depth: 0,
+                g: 0,
+                h: 0,
+            },
+        );
+    }
+
+    let mut frontiers = [
+        VecDeque::from([initial_key.clone()]),
+        VecDeque::from([goal_key.clone()]),
+    ];
+
+    // The meeting state with the lowest combined cost found so far.
+    let mut meeting: Option<(P::Key, usize)> = None;
+
+    if initial_key == goal_key {
+        let id = tracer.id(&(false, initial_key.clone()));
+        tracer.emit(SearchEvent::GoalFound { id });
+        meeting = Some((initial_key, 0));
+    }
+
+    while meeting.is_none() && !frontiers[0].is_empty() && !frontiers[1].is_empty() {
+        let side = if frontiers[0].len() <= frontiers[1].len() {
+            0
+        } else {
+            1
+        };
+        let other = 1 - side;
+
+        // Finish the whole layer before stopping, since a later node in the
+        // same layer may meet the other side at a lower cost.
+        for key in std::mem::take(&mut frontiers[side]) {
+            if tracer.interrupted() {
+                stats.finish(started, None);
+                return SearchResult::interrupted(stats);
+            }
+
+            stats.nodes_expanded += 1;
+
+            let id = tracer.id(&(side == 1, key.clone()));
+            tracer.emit(SearchEvent::NodeExpanded { id });
+
+            let parent = trees[side].index_of(&key).unwrap();
+            let current = &trees[side].tree.nodes()[parent];
+            let (state, depth, g) = (current.state.clone(), current.depth, current.g);
+
+            let neighbours = if side == 0 {
+                problem.successors(&state)
+            } else {
+                problem.predecessors(&state)
+            };
+
+            for (action, next, cost) in neighbours {
+                stats.nodes_generated += 1;
+
+                let next_key = problem.key(&next);
+
+                if trees[side].contains(&next_key) {
+                    stats.duplicates_pruned += 1;
+                    let known = tracer.id(&(side == 1, next_key));
+                    tracer.emit(SearchEvent::DuplicatePruned {
+                        id: known,
+                        parent: id,
+                    });
+                    continue;
+                }
+
+                let next_id = tracer.id(&(side == 1, next_key.clone()));
+                tracer.emit(SearchEvent::NodeGenerated {
+                    id: next_id,
+                    parent: Some((id, &action)),
+                    state: &next,
+                    depth: depth + 1,
+                    g: g + cost,
+                    h: None,
+                    backward: side == 1,
+                });
+
+                if let Some(met) = trees[other].get(&next_key) {
+                    tracer.emit(SearchEvent::GoalFound { id: next_id });
+
+                    let total = g + cost + met.g;
+                    if meeting.as_ref().is_none_or(|(_, best)| total < *best) {
+                        meeting = Some((next_key.clone(), total));
+                    }
+                }
+
+                let node_bytes = problem.state_bytes(&next);
+                trees[side].insert(
+                    next_key.clone(),
+                    Node {
+                        state: next,
+                        parent: Some(parent),
+                        action: Some(action),
+                        depth: depth + 1,
+                        g: g + cost,
+                        h: 0,
+                    },
+                );
+                frontiers[side].push_back(next_key);
+                stats.observe(
+                    frontiers[0].len() + frontiers[1].len(),
+                    trees[0].len() + trees[1].len(),
+                    node_bytes,
+                );
+            }
+
+            tracer.emit(SearchEvent::FrontierSnapshot {
+                frontier: frontiers[0].len() + frontiers[1].len(),
+                stored: trees[0].len() + trees[1].len(),
+            });
+        }
+    }
+
+    let (meeting_key, cost) = match meeting {
+        Some(meeting) => meeting,
+        None => {
+            stats.finish(started, None);
+            return SearchResult::failure(stats);
+        }
+    };
+
+    let [forward, backward] = trees;
+
+    let (mut actions, mut states) = forward
+        .tree
+        .path_to(forward.index_of(&meeting_key).unwrap());
+
+    let mut current = backward.get(&meeting_key).unwrap();
+    while let (Some(parent), Some(action)) = (current.parent, &current.action) {
+        actions.push(action.clone());
+        current = &backward.tree.nodes()[parent];
+        states.push(current.state.clone());
+    }
+
+    stats.finish(started, Some(actions.len()));
+
+    SearchResult {
+        found: true,
+        interrupted: false,
+        actions,
+        states,
+        cost,
+        stats,
+    }
+}